@@ -3,7 +3,11 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use secret_prisoner_game_contract::msg::{HandleMsg, InitMsg, QueryMsg};
+use secret_prisoner_game_contract::error::ContractErrorResponse;
+use secret_prisoner_game_contract::msg::{
+    HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, QueryWithPermit, ReceiveMsg,
+};
+use secret_prisoner_game_contract::types::{Color, GameResult, Guess, Hint, RoundResult, Shape, Target};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,4 +18,20 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(QueryWithPermit), &out_dir);
+    export_schema(&schema_for!(HandleAnswer), &out_dir);
+    export_schema(&schema_for!(QueryAnswer), &out_dir);
+
+    // errors, the description of each error is its code
+    export_schema(&schema_for!(ContractErrorResponse), &out_dir);
+
+    // typed values used by the v2 messages
+    export_schema(&schema_for!(Color), &out_dir);
+    export_schema(&schema_for!(Shape), &out_dir);
+    export_schema(&schema_for!(Hint), &out_dir);
+    export_schema(&schema_for!(Target), &out_dir);
+    export_schema(&schema_for!(Guess), &out_dir);
+    export_schema(&schema_for!(RoundResult), &out_dir);
+    export_schema(&schema_for!(GameResult), &out_dir);
 }
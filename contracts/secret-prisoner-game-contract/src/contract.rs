@@ -1,34 +1,52 @@
 use std::cmp::{max};
 use cosmwasm_std::{
-    debug_print, 
-    to_binary, Api, Binary, Coin, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier,
-    StdError, StdResult, Storage, CanonicalAddr, Uint128, CosmosMsg, BankMsg,
+    debug_print, log,
+    to_binary, from_binary, Api, Binary, Coin, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier,
+    StdError, StdResult, Storage, CanonicalAddr, Uint128, CosmosMsg, BankMsg, LogAttribute,
 };
 use secret_toolkit::{
     permit::{validate, Permission, Permit, RevokedPermits},
+    snip20::{register_receive_msg, transfer_msg, balance_query, set_viewing_key_msg as set_token_viewing_key_msg},
     snip721::{
         mint_nft_msg, Metadata, set_viewing_key_msg, register_receive_nft_msg, private_metadata_query,
         ViewerInfo, Extension,
     },
 };
 
-use crate::msg::{ContractInfo, GameStateResponse, QueryWithPermit, HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, space_pad, ResponseStatus::Success};
-use crate::random::{get_random_number, supply_more_entropy, sha_256};
+use crate::error::ContractError;
+use crate::msg::{ConfigUpdate, ContractInfo, ContractStatus, DealResponse, DenomStakes, FeeResponse, JackpotRule, PendingWithdrawalResponse, PoolChangeResponse, SolvencyResponse, GameStateResponse, GameStateV2Response, GameSummaryResponse, LeaderboardPlayerResponse, RoundHistoryResponse, RoundHistoryV2Response, RoundRevealResponse, RoundRevealV2Response, TranscriptEvent, TranscriptPlayer, RoundSummaryResponse, QueryWithPermit, HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, ReceiveMsg, space_pad, ResponseStatus::Success};
+use crate::random::{supply_more_entropy, sha_256, Prng};
 use crate::state::{
-    create_new_game, set_config, get_config, get_current_game, get_game_state, get_number_of_games,
-    GameState, create_new_round, update_game_state, RoundState, Config, set_current_game, get_pool, set_pool,
-    StoreContractInfo, set_minter, get_minter,
+    create_new_game, create_new_private_game, set_config, get_config, get_current_game, get_game_state,
+    GameState, create_new_round, update_game_state, RoundState, Config, set_current_game, get_pool, add_to_pool, take_from_pool,
+    StoreContractInfo, set_minter, get_minter, get_open_lobby_games, get_lobby, set_lobby, remove_from_lobby,
+    get_player_stats, record_player_outcome, get_player_games, get_leaderboard, set_leaderboard_opt_in,
+    PlayerStats, LeaderboardEntry, LobbyEntry, get_balance, set_balance, credit_balance, DenomConfig,
+    FeeRecord, StoredJackpotRule, PoolChange, get_pool_log, PendingWithdrawal, set_pending_withdrawal,
+    get_pending_withdrawal, remove_pending_withdrawal, add_escrow, release_escrow, get_escrow, get_balance_total,
 };
-use crate::types::{Chip, Guess, Hint, RoundStage, RoundResult, Target, Color, Shape, GameResult,
+use crate::types::{Chip, Guess, Hint, RoundStage, RoundResult, RoundOutcome, Target, Color, Shape, GameResult, PlayerOutcome, StakesTier, LobbyPolicy, PoolChangeReason,
 //RED, GREEN, BLUE, BLACK, TRIANGLE, SQUARE, CIRCLE, STAR, 
 REWARD_NFT, REWARD_POOL, POWERUP_INSURANCE,};
 
 /// We make sure that responses from `handle` are padded to a multiple of this size.
 pub const RESPONSE_BLOCK_SIZE: usize = 256;
 pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
-pub const DEFAULT_STAKES: Uint128 = Uint128(1000000);
+pub const DEFAULT_LOW_STAKES: Uint128 = Uint128(1000000);
+pub const DEFAULT_MEDIUM_STAKES: Uint128 = Uint128(5000000);
+pub const DEFAULT_HIGH_STAKES: Uint128 = Uint128(10000000);
 pub const DEFAULT_TIMEOUT: u64 = 100; // 100 Blocks (~ 10 minutes)
+pub const MAX_ROUNDS_PER_GAME: u8 = 10;
+pub const DEFAULT_LOBBY_EXPIRATION: u64 = 600; // 600 Blocks (~ 1 hour)
 pub const DENOM: &str = "uscrt";
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+pub const MAX_RAKE_BPS: u16 = 1000; // 10%
+pub const DEFAULT_JACKPOT_BPS: u16 = 5000; // half of the pool
+pub const DEFAULT_POOL_WITHDRAWAL_DELAY: u64 = 14400; // 14400 Blocks (~ 1 day)
+pub const MIN_POOL_WITHDRAWAL_DELAY: u64 = 600; // 600 Blocks (~ 1 hour)
+pub const MAX_POOL_WITHDRAWAL_DELAY: u64 = 432000; // 432000 Blocks (~ 30 days)
+pub const DEFAULT_PAGE_SIZE: u32 = 10;
+pub const MAX_PAGE_SIZE: u32 = 50;
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -48,15 +66,45 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let circle_weight = msg.circle_weight.unwrap_or(25);
     let star_weight = msg.star_weight.unwrap_or(25);
 
-    let stakes = msg.stakes.unwrap_or(DEFAULT_STAKES);
-    let stakes = stakes.u128();
+    let low_stakes = msg.low_stakes.unwrap_or(DEFAULT_LOW_STAKES).u128();
+    let medium_stakes = msg.medium_stakes.unwrap_or(DEFAULT_MEDIUM_STAKES).u128();
+    let high_stakes = msg.high_stakes.unwrap_or(DEFAULT_HIGH_STAKES).u128();
 
     // default timeout for each move is 20 blocks
     let timeout = msg.timeout.unwrap_or(DEFAULT_TIMEOUT);
 
-    let admin = deps.api.canonical_address(&env.message.sender)?;
+    let lobby_policy = parse_lobby_policy(msg.lobby_policy.clone())?;
+    let lobby_expiration = msg.lobby_expiration.unwrap_or(DEFAULT_LOBBY_EXPIRATION);
+
+    let rounds_per_game = msg.rounds_per_game;
+
+    let admin = match msg.admin.clone() {
+        Some(admin) => deps.api.canonical_address(&admin)?,
+        None => deps.api.canonical_address(&env.message.sender)?,
+    };
     let contract_address = deps.api.canonical_address(&env.contract.address)?;
 
+    let token = match &msg.token {
+        Some(token) => Some(StoreContractInfo {
+            address: deps.api.canonical_address(&token.address)?,
+            code_hash: token.code_hash.clone(),
+        }),
+        None => None,
+    };
+
+    // the default denom is uscrt, or the snip-20 token's address
+    let default_denom = match &msg.token {
+        Some(token) => token.address.as_str().to_string(),
+        None => DENOM.to_string(),
+    };
+    let mut denoms = vec![DenomConfig { denom: default_denom, low_stakes, medium_stakes, high_stakes }];
+    denoms.extend(denom_configs(msg.denoms.clone().unwrap_or_default()));
+
+    let jackpot_rule = match &msg.jackpot_rule {
+        Some(jackpot_rule) => jackpot_rule.to_stored()?,
+        None => StoredJackpotRule::Fraction { bps: DEFAULT_JACKPOT_BPS },
+    };
+
     let config = Config {
         admin,
         contract_address,
@@ -68,10 +116,31 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         square_weight,
         circle_weight,
         star_weight,
-        stakes,
+        denoms,
         timeout,
+        rounds_per_game,
+        lobby_policy: lobby_policy.u8_val(),
+        lobby_expiration,
+        status: ContractStatus::Normal.to_u8(),
         viewing_key: viewing_key.clone(),
+        token,
+        rake_bps: 0,
+        rake_pool_bps: 10000,
+        rake_treasury_bps: 0,
+        rake_referral_bps: 0,
+        treasury: None,
+        jackpot_rule,
+        pool_withdrawal_delay: msg.pool_withdrawal_delay.unwrap_or(DEFAULT_POOL_WITHDRAWAL_DELAY),
     };
+    validate_config(&config)?;
+
+    // are the jackpot pools seeded with funds?
+    for funds in &env.message.sent_funds {
+        if config.denom_config(&funds.denom).is_none() {
+            return Err(ContractError::CannotSeedPool { denom: funds.denom.clone() }.into());
+        }
+        add_to_pool(&mut deps.storage, &funds.denom, funds.amount.u128(), PoolChangeReason::Seed, None, env.block.height)?;
+    }
 
     set_config(
         &mut deps.storage, 
@@ -85,28 +154,33 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     };
     set_minter(&mut deps.storage, minter)?;
 
-    // is the jackpot pool seeded with funds?
-    if env.message.sent_funds.len() == 0 {
-        set_pool(&mut deps.storage, 0)?;
-    } else if env.message.sent_funds.len() == 1 {
-        let funds = &env.message.sent_funds[0];
-        if funds.denom != DENOM {
-            return Err(StdError::generic_err("Can only seed jackpot pool with scrt"));
-        } else {
-            set_pool(&mut deps.storage, funds.amount.u128())?;
-        }
-    } else {
-        return Err(StdError::generic_err("Can only seed jackpot pool with scrt"));
-    }
-
     let mut fresh_entropy = to_binary(&msg)?.0;
     fresh_entropy.extend(to_binary(&env)?.0);
     supply_more_entropy(&mut deps.storage, fresh_entropy.as_slice())?;
 
     debug_print!("Contract was initialized by {}", env.message.sender);
 
+    let mut messages = vec![];
+    if let Some(token) = &msg.token {
+        messages.push(register_receive_msg(
+            env.contract_code_hash.clone(),
+            None,
+            256,
+            token.code_hash.clone(),
+            token.address.clone(),
+        )?);
+        // lets the solvency query read the contract's token balance
+        messages.push(set_token_viewing_key_msg(
+            viewing_key.clone(),
+            None,
+            256,
+            token.code_hash.clone(),
+            token.address.clone(),
+        )?);
+    }
+
     Ok(InitResponse {
-        messages: vec![
+        messages: [messages, vec![
             register_receive_nft_msg(
                 env.contract_code_hash,
                 Some(true),
@@ -129,11 +203,48 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
             //    msg.minter.code_hash,
             //    msg.minter.address,
             //)?,
-        ],
+        ]].concat(),
         log: vec![],
     })
 }
 
+fn validate_config(config: &Config) -> Result<(), ContractError> {
+    let weights = [
+        config.red_weight, config.green_weight, config.blue_weight, config.black_weight,
+        config.triangle_weight, config.square_weight, config.circle_weight, config.star_weight,
+    ];
+    if weights.iter().any(|weight| *weight == 0) {
+        return Err(ContractError::InvalidWeights {});
+    }
+    for (i, denom) in config.denoms.iter().enumerate() {
+        if denom.denom.is_empty() || config.denoms[..i].iter().any(|other| other.denom == denom.denom) {
+            return Err(ContractError::InvalidDenoms {});
+        }
+        if denom.low_stakes == 0 || denom.low_stakes > denom.medium_stakes || denom.medium_stakes > denom.high_stakes {
+            return Err(ContractError::InvalidDenomStakes { denom: denom.denom.clone() });
+        }
+    }
+    if config.timeout == 0 {
+        return Err(ContractError::InvalidTimeout {});
+    }
+    if config.rounds_per_game == 0 || config.rounds_per_game > MAX_ROUNDS_PER_GAME {
+        return Err(ContractError::InvalidRoundsPerGame { max: MAX_ROUNDS_PER_GAME });
+    }
+    if config.lobby_expiration == 0 {
+        return Err(ContractError::InvalidLobbyExpiration {});
+    }
+    if config.rake_bps > MAX_RAKE_BPS {
+        return Err(ContractError::RakeTooHigh { max_bps: MAX_RAKE_BPS });
+    }
+    if config.rake_pool_bps as u32 + config.rake_treasury_bps as u32 + config.rake_referral_bps as u32 != 10000 {
+        return Err(ContractError::InvalidFeeRouting {});
+    }
+    if config.pool_withdrawal_delay < MIN_POOL_WITHDRAWAL_DELAY || config.pool_withdrawal_delay > MAX_POOL_WITHDRAWAL_DELAY {
+        return Err(ContractError::InvalidPoolWithdrawalDelay { min: MIN_POOL_WITHDRAWAL_DELAY, max: MAX_POOL_WITHDRAWAL_DELAY });
+    }
+    Ok(())
+}
+
 fn pad_response(response: StdResult<HandleResponse>) -> StdResult<HandleResponse> {
     response.map(|mut response| {
         response.data = response.data.map(|mut data| {
@@ -151,91 +262,536 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     let mut fresh_entropy = to_binary(&msg)?.0;
     fresh_entropy.extend(to_binary(&env)?.0);
-    supply_more_entropy(&mut deps.storage, fresh_entropy.as_slice())?;
+    // every random draw in the transaction comes from this rng
+    let mut rng = supply_more_entropy(&mut deps.storage, fresh_entropy.as_slice())?;
+
+    // new games need Normal status, moves in games in progress are allowed until StopAll
+    let status = get_config(&deps.storage)?.status;
+    let priority = match &msg {
+        HandleMsg::Join { .. } |
+        HandleMsg::CreatePrivateGame { .. } |
+        HandleMsg::JoinPrivateGame { .. } |
+        HandleMsg::Deposit { .. } |
+        HandleMsg::FundPool { .. } |
+        HandleMsg::Receive { .. } => ContractStatus::Normal.to_u8(),
+        HandleMsg::Submit { .. } |
+        HandleMsg::Guess { .. } |
+        HandleMsg::SubmitV2 { .. } |
+        HandleMsg::GuessV2 { .. } |
+        HandleMsg::PickReward { .. } |
+        HandleMsg::Withdraw { .. } |
+        HandleMsg::ForceEndgame { .. } |
+        HandleMsg::BatchReceiveNft { .. } => ContractStatus::StopTransactions.to_u8(),
+        _ => ContractStatus::StopAll.to_u8(),
+    };
+    if priority < status {
+        return Err(ContractError::ActionDisabled {}.into());
+    }
 
     let response = match msg {
-        HandleMsg::Join { 
-            //stakes, 
-            .. 
-        } => try_join(deps, env,),
-        HandleMsg::Submit { target, color, shape, .. } => try_submit(deps, env, target, color, shape),
-        HandleMsg::Guess { target, color, shape, .. } => try_guess(deps, env, target, color, shape),
-        HandleMsg::PickReward { reward, .. } => try_pick_reward(deps, env, reward),
-        HandleMsg::Withdraw { .. } => try_withdraw(deps, env),
-        HandleMsg::ForceEndgame { .. } => try_force_endgame(deps, env),
+        HandleMsg::Join { stakes, entropy, entropy_commitment, from_balance, denom, referrer, v2, .. } => {
+            let player = env.message.sender.clone();
+            try_join(deps, env, &mut rng, player, None, stakes, entropy, entropy_commitment, from_balance, denom, referrer, v2)
+        },
+        HandleMsg::CreatePrivateGame { stakes, opponent, invite_code, entropy, entropy_commitment, from_balance, denom, referrer, v2, .. } => {
+            let player = env.message.sender.clone();
+            try_create_private_game(deps, env, player, None, stakes, opponent, invite_code, entropy, entropy_commitment, from_balance, denom, referrer, v2)
+        },
+        HandleMsg::JoinPrivateGame { host, invite_code, entropy, entropy_commitment, from_balance, denom, referrer, v2, .. } => {
+            let player = env.message.sender.clone();
+            try_join_private_game(deps, env, &mut rng, player, None, host, invite_code, entropy, entropy_commitment, from_balance, denom, referrer, v2)
+        },
+        HandleMsg::Submit { target, color, shape, .. } => try_submit(deps, env, &mut rng, target, color, shape),
+        HandleMsg::Guess { target, color, shape, .. } => try_guess(deps, env, &mut rng, target, color, shape),
+        HandleMsg::SubmitV2 { hint, .. } => try_submit_v2(deps, env, &mut rng, hint),
+        HandleMsg::GuessV2 { guess, .. } => try_guess_v2(deps, env, &mut rng, guess),
+        HandleMsg::PickReward { reward, v2, .. } => try_pick_reward(deps, env, &mut rng, reward, v2),
+        HandleMsg::Withdraw { v2, .. } => try_withdraw(deps, env, v2),
+        HandleMsg::ForceEndgame { v2, .. } => try_force_endgame(deps, env, v2),
+        HandleMsg::ClaimRefund { v2, .. } => try_claim_refund(deps, env, v2),
         HandleMsg::BatchReceiveNft { sender, from, token_ids, msg } => try_receive_nft(deps, env, sender, from, token_ids, msg),
+        HandleMsg::Receive { sender, from, amount, msg } => try_receive(deps, env, &mut rng, sender, from, amount, msg),
         HandleMsg::RevokePermit { permit_name, .. } => revoke_permit(deps, env, permit_name),
+        HandleMsg::Claim { amount, denom, .. } => try_claim(deps, env, amount, denom),
+        HandleMsg::Deposit { .. } => {
+            let player = env.message.sender.clone();
+            try_deposit(deps, env, player, None)
+        },
+        HandleMsg::FundPool { .. } => try_fund_pool(deps, env, None),
+        HandleMsg::SetLeaderboardOptIn { opt_in, display_name, .. } => try_set_leaderboard_opt_in(deps, env, opt_in, display_name),
+        HandleMsg::UpdateConfig { config, .. } => try_update_config(deps, env, config),
+        HandleMsg::ChangeAdmin { address, .. } => try_change_admin(deps, env, address),
+        HandleMsg::SetMinter { minter, .. } => try_set_minter(deps, env, minter),
+        HandleMsg::SetRake { rake_bps, .. } => try_set_rake(deps, env, rake_bps),
+        HandleMsg::SetFeeRouting { pool_bps, treasury_bps, referral_bps, treasury, .. } => 
+            try_set_fee_routing(deps, env, pool_bps, treasury_bps, referral_bps, treasury),
+        HandleMsg::SetJackpotRule { rule, .. } => try_set_jackpot_rule(deps, env, rule),
+        HandleMsg::ProposePoolWithdrawal { amount, denom, recipient, .. } => try_propose_pool_withdrawal(deps, env, amount, denom, recipient),
+        HandleMsg::ExecutePoolWithdrawal { denom, .. } => try_execute_pool_withdrawal(deps, env, denom),
+        HandleMsg::SetContractStatus { level, .. } => try_set_contract_status(deps, env, level),
     };
 
-    pad_response(response)
+    // contract errors are turned into a StdError with the error code here
+    pad_response(response.map_err(StdError::from))
 }
 
-pub fn try_join<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    //stakes: Option<String>,
-) -> StdResult<HandleResponse> {
-    let player = deps.api.canonical_address(&env.message.sender)?;
-
+fn check_not_in_game<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    block: u64,
+) -> Result<(), ContractError> {
     // check if already in ongoing game, 
     // if yes, check it is finished otherwise throw error (only one game at a time allowed)
-    let current_game_idx = get_current_game(&deps.storage, &player);
+    let current_game_idx = get_current_game(storage, player);
     if current_game_idx.is_some() {
-        let current_game = get_game_state(&deps.storage, current_game_idx.unwrap())?;
+        let game_idx = current_game_idx.unwrap();
+        let mut current_game = get_game_state(storage, game_idx)?;
+        if !current_game.finished && lobby_game_expired(&current_game, block) {
+            // nobody joined in time, refund the wager so the player can start again
+            settle_game(storage, game_idx, &mut current_game, Settlement::Refunded, block)?;
+            update_game_state(storage, game_idx, &current_game)?;
+        }
         if !current_game.finished {
-            return Err(StdError::generic_err("You must finish current game before beginning a new one"));
+            return Err(ContractError::AlreadyInGame {});
         }
     }
+    Ok(())
+}
 
-    // check that player has sent correct funds to match the stakes
-    let stakes = get_config(&deps.storage)?.stakes;
-    if env.message.sent_funds.len() != 1 {
-        return Err(StdError::generic_err("Incorrect funds sent to join game"));
+// a game in the lobby expires once it has waited the lobby expiration it was created with
+// without an opponent
+fn lobby_game_expired(
+    game_state: &GameState,
+    block: u64,
+) -> bool {
+    if game_state.player_b.is_some() || game_state.is_private() {
+        return false;
     }
-    let funds = &env.message.sent_funds[0];
-    if funds.denom != "uscrt" {
-        return Err(StdError::generic_err("Incorrect coin type sent to join game"));
+    block >= game_state.created_block + game_state.lobby_expiration
+}
+
+// entropy given when joining is kept as its sha256 hash, which must match the commitment if given
+fn parse_player_entropy(
+    entropy: Option<String>,
+    entropy_commitment: Option<String>,
+) -> Result<Option<Vec<u8>>, ContractError> {
+    match (entropy, entropy_commitment) {
+        (None, None) => Ok(None),
+        (None, Some(_)) => Err(ContractError::CommitmentWithoutEntropy {}),
+        (Some(entropy), entropy_commitment) => {
+            if entropy.is_empty() {
+                return Err(ContractError::EmptyEntropy {});
+            }
+            let entropy_hash = sha_256(entropy.as_bytes());
+            if let Some(entropy_commitment) = entropy_commitment {
+                if hex::encode(&entropy_hash) != entropy_commitment.to_lowercase() {
+                    return Err(ContractError::EntropyMismatch {});
+                }
+            }
+            Ok(Some(entropy_hash.to_vec()))
+        },
     }
-    if funds.amount.u128() != stakes {
-        return Err(StdError::generic_err(format!("Incorrect amount sent, must be {} uscrt", stakes)));
+}
+
+// the referrer given when joining, players cannot refer themselves
+fn parse_referrer<A: Api>(
+    api: &A,
+    player: &CanonicalAddr,
+    referrer: Option<HumanAddr>,
+) -> Result<Option<CanonicalAddr>, ContractError> {
+    match referrer {
+        None => Ok(None),
+        Some(referrer) => {
+            let referrer = api.canonical_address(&referrer)?;
+            if referrer == *player {
+                return Err(ContractError::ReferSelf {});
+            }
+            Ok(Some(referrer))
+        },
     }
+}
 
-    let number_of_games = get_number_of_games(&deps.storage)?;
-    let game_ready: bool;
-    let mut game_state: Option<GameState> = None;
+fn parse_lobby_policy(lobby_policy: Option<String>) -> Result<LobbyPolicy, ContractError> {
+    match lobby_policy.as_deref() {
+        None | Some("fifo") => Ok(LobbyPolicy::Fifo),
+        Some("random") => Ok(LobbyPolicy::Random),
+        Some(_) => Err(ContractError::InvalidLobbyPolicy {}),
+    }
+}
 
-    // check if a new game needs to be created
-    if number_of_games == 0 {
-        game_ready = false;
+// take an open game in the stakes tier out of the lobby, according to the lobby policy
+fn pick_lobby_game<S: Storage>(
+    storage: &mut S,
+    rng: &mut Prng,
+    stakes: u8,
+    denom: &str,
+    wager: u128,
+    block: u64,
+) -> Result<Option<u32>, ContractError> {
+    let config = get_config(storage)?;
+    // stale entries are dropped whenever the lobby is read for pairing
+    let (mut open_games, expired_games) = get_open_lobby_games(storage, stakes, denom, block)?;
+    // games nobody joined in time are finished and their wagers refunded
+    for entry in expired_games {
+        let mut game_state = get_game_state(storage, entry.game_idx)?;
+        settle_game(storage, entry.game_idx, &mut game_state, Settlement::Refunded, block)?;
+        update_game_state(storage, entry.game_idx, &game_state)?;
+    }
+    // games keep the wager they were opened with, so only games with the same wager are paired.
+    // games opened before the stakes were changed wait until they are withdrawn or expire
+    let same_wager: Vec<usize> = open_games.iter()
+        .enumerate()
+        .filter(|(_, (_, game_wager))| *game_wager == wager)
+        .map(|(i, _)| i)
+        .collect();
+    let game_idx = if same_wager.is_empty() {
+        None
     } else {
-        let current_game_state = get_game_state(&deps.storage, number_of_games - 1)?;
-        game_ready = current_game_state.player_b.is_none();
-        game_state = Some(current_game_state);
+        let pick = match LobbyPolicy::from_u8(config.lobby_policy)? {
+            LobbyPolicy::Fifo => same_wager[0],
+            LobbyPolicy::Random => same_wager[(rng.next_u64() % same_wager.len() as u64) as usize],
+        };
+        Some(open_games.remove(pick).0.game_idx)
+    };
+    let open_games: Vec<LobbyEntry> = open_games.into_iter().map(|(entry, _)| entry).collect();
+    set_lobby(storage, stakes, denom, &open_games)?;
+    Ok(game_idx)
+}
+
+fn parse_stakes(stakes: Option<String>) -> Result<StakesTier, ContractError> {
+    match stakes.as_deref() {
+        None | Some("low") => Ok(StakesTier::Low),
+        Some("medium") => Ok(StakesTier::Medium),
+        Some("high") => Ok(StakesTier::High),
+        Some("practice") => Ok(StakesTier::Practice),
+        Some(_) => Err(ContractError::InvalidStakes {}),
     }
-    
-    if !game_ready {
+}
+
+// the denom and amount sent with the message, a coin in an accepted native denom or the
+// snip-20 amount received through Receive, None if nothing was sent
+fn sent_funds(
+    env: &Env,
+    config: &Config,
+    received: Option<(String, u128)>,
+) -> Result<Option<(String, u128)>, ContractError> {
+    if received.is_some() {
+        return Ok(received);
+    }
+    match env.message.sent_funds.len() {
+        0 => Ok(None),
+        1 => {
+            let funds = &env.message.sent_funds[0];
+            if config.denom_config(&funds.denom).is_none() {
+                return Err(ContractError::DenomNotAccepted { denom: funds.denom.clone() });
+            }
+            Ok(Some((funds.denom.clone(), funds.amount.u128())))
+        },
+        _ => Err(ContractError::MultipleDenoms {}),
+    }
+}
+
+// denom of the wager: the denom of the sent funds, else the requested denom, else the default denom
+fn wager_denom(
+    config: &Config,
+    sent: &Option<(String, u128)>,
+    denom: Option<String>,
+) -> Result<String, ContractError> {
+    match (sent, denom) {
+        (Some((sent_denom, _)), Some(denom)) if *sent_denom != denom => {
+            Err(ContractError::WrongDenom { sent: sent_denom.clone(), expected: denom })
+        },
+        (Some((sent_denom, _)), _) => Ok(sent_denom.clone()),
+        (None, Some(denom)) => Ok(denom),
+        (None, None) => Ok(config.default_denom().to_string()),
+    }
+}
+
+// take the wager either from the sent funds or from the player's deposited balance
+fn take_wager<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    sent: Option<(String, u128)>,
+    denom: &str,
+    wager: u128,
+    from_balance: Option<bool>,
+) -> Result<(), ContractError> {
+    let sent_amount = sent.map(|(_, amount)| amount).unwrap_or(0);
+    if from_balance.unwrap_or(false) {
+        if sent_amount > 0 {
+            return Err(ContractError::FundsWithBalance {});
+        }
+        let balance = get_balance(storage, player, denom);
+        if balance < wager {
+            return Err(ContractError::InsufficientBalance { amount: Uint128(wager), denom: denom.to_string() });
+        }
+        set_balance(storage, player, denom, balance - wager)?;
+    } else if wager == 0 {
+        if sent_amount > 0 {
+            return Err(ContractError::FundsForPractice {});
+        }
+    } else if sent_amount != wager {
+        return Err(ContractError::IncorrectAmount { amount: Uint128(wager), denom: denom.to_string() });
+    }
+    // wager is held in escrow until the game is finished
+    add_escrow(storage, denom, wager)?;
+    Ok(())
+}
+
+// send amount of denom to recipient, as a token transfer if denom is the configured snip-20 token
+fn payout_msg<A: Api>(
+    api: &A,
+    config: &Config,
+    contract_address: HumanAddr,
+    recipient: HumanAddr,
+    denom: &str,
+    amount: u128,
+) -> Result<CosmosMsg, ContractError> {
+    if let Some(token) = &config.token {
+        let token_address = api.human_address(&token.address)?;
+        if token_address.as_str() == denom {
+            return Ok(transfer_msg(
+                recipient,
+                Uint128(amount),
+                None,
+                RESPONSE_BLOCK_SIZE,
+                token.code_hash.clone(),
+                token_address,
+            )?);
+        }
+    }
+    Ok(CosmosMsg::Bank(BankMsg::Send {
+        from_address: contract_address,
+        to_address: recipient,
+        amount: vec![Coin {
+            denom: denom.to_string(),
+            amount: Uint128(amount),
+        }],
+    }))
+}
+
+fn denom_configs(denoms: Vec<DenomStakes>) -> Vec<DenomConfig> {
+    denoms.into_iter().map(|denom| DenomConfig {
+        denom: denom.denom,
+        low_stakes: denom.low_stakes.u128(),
+        medium_stakes: denom.medium_stakes.u128(),
+        high_stakes: denom.high_stakes.u128(),
+    }).collect()
+}
+
+// add player_b to waiting game_state, create first round and assign chips
+fn add_second_player<S: Storage>(
+    storage: &mut S,
+    rng: &mut Prng,
+    game_idx: u32,
+    mut game_state: GameState,
+    player: &CanonicalAddr,
+    wager: u128,
+    entropy: Option<Vec<u8>>,
+    referrer: Option<CanonicalAddr>,
+    block: u64,
+) -> Result<(), ContractError> {
+    game_state.player_b = Some(player.clone());
+    game_state.player_b_wager = Some(wager);
+    game_state.player_b_entropy = entropy;
+    game_state.player_b_referrer = referrer;
+
+    let new_round = create_new_round(rng, block, &game_state.chip_weights, &game_state.players_entropy())?;
+    game_state.round_state = Some(new_round);
+    game_state.round = 1_u8;
+    update_game_state(storage, game_idx, &game_state)?;
+    set_current_game(storage, player, Some(game_idx))?;
+    Ok(())
+}
+
+pub fn try_join<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    player: HumanAddr,
+    received: Option<(String, u128)>,
+    stakes: Option<String>,
+    entropy: Option<String>,
+    entropy_commitment: Option<String>,
+    from_balance: Option<bool>,
+    denom: Option<String>,
+    referrer: Option<HumanAddr>,
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&player)?;
+    check_not_in_game(&mut deps.storage, &player, env.block.height)?;
+    let entropy = parse_player_entropy(entropy, entropy_commitment)?;
+    let referrer = parse_referrer(&deps.api, &player, referrer)?;
+
+    let stakes: StakesTier = parse_stakes(stakes)?;
+    let config = get_config(&deps.storage)?;
+    let sent = sent_funds(&env, &config, received)?;
+    let denom = wager_denom(&config, &sent, denom)?;
+    let wager = config.stakes_for_tier(&denom, &stakes)?;
+    take_wager(&mut deps.storage, &player, sent, &denom, wager, from_balance)?;
+
+    // check if a new game needs to be created, only pair with a game in the same stakes tier and denom
+    let lobby_game = pick_lobby_game(&mut deps.storage, rng, stakes.u8_val(), &denom, wager, env.block.height)?;
+    // a paired game was waiting for this player
+    let from_stage = lobby_game.map(|_| "waiting".to_string());
+
+    if lobby_game.is_none() {
         // if yes: create a new game state with player_a
-        //   create_new_game sets the current game for player to this one
-        create_new_game(&mut deps.storage, &player, funds.amount.u128())?;
+        //   create_new_game sets the current game for player to this one, 
+        //   and adds it to the lobby for the stakes tier
+        create_new_game(&mut deps.storage, &player, stakes.u8_val(), &denom, wager, entropy, referrer, env.block.height)?;
     } else {
-        // if no: add player_b to waiting game_state, create first round and assign chips
-        let mut game_state = game_state.unwrap();
-        game_state.player_b = Some(player.clone());
-        game_state.player_b_wager = Some(funds.amount.u128());
+        // if no: join the open game picked from the lobby
+        let game_idx = lobby_game.unwrap();
+        let game_state = get_game_state(&deps.storage, game_idx)?;
+        add_second_player(&mut deps.storage, rng, game_idx, game_state, &player, wager, entropy, referrer, env.block.height)?;
+    }
+
+    let game_idx = get_current_game(&deps.storage, &player).unwrap();
+    let log = game_log(&deps.storage, "join", game_idx, from_stage)?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::Join { status: Success, game_state, game_state_v2 })?),
+    })
+}
 
-        let new_round = create_new_round(&deps.storage, env.block.height)?;
-        game_state.round_state = Some(new_round);
-        game_state.round = 1_u8;
-        update_game_state(&mut deps.storage, number_of_games - 1, &game_state)?;
-        set_current_game(&mut deps.storage, &player, Some(number_of_games - 1))?;
+pub fn try_create_private_game<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    player: HumanAddr,
+    received: Option<(String, u128)>,
+    stakes: Option<String>,
+    opponent: Option<HumanAddr>,
+    invite_code: Option<String>,
+    entropy: Option<String>,
+    entropy_commitment: Option<String>,
+    from_balance: Option<bool>,
+    denom: Option<String>,
+    referrer: Option<HumanAddr>,
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&player)?;
+    check_not_in_game(&mut deps.storage, &player, env.block.height)?;
+    let entropy = parse_player_entropy(entropy, entropy_commitment)?;
+    let referrer = parse_referrer(&deps.api, &player, referrer)?;
+
+    if opponent.is_none() && invite_code.is_none() {
+        return Err(ContractError::PrivateGameWithoutOpponent {});
     }
 
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
+    let invited_player: Option<CanonicalAddr> = match opponent {
+        Some(opponent) => {
+            let opponent = deps.api.canonical_address(&opponent)?;
+            if opponent == player {
+                return Err(ContractError::ChallengeSelf {});
+            }
+            Some(opponent)
+        },
+        None => None,
+    };
+    let invite_hash: Option<Vec<u8>> = match invite_code {
+        Some(invite_code) => {
+            if invite_code.is_empty() {
+                return Err(ContractError::EmptyInviteCode {});
+            }
+            Some(sha_256(invite_code.as_bytes()).to_vec())
+        },
+        None => None,
+    };
+
+    let stakes: StakesTier = parse_stakes(stakes)?;
+    let config = get_config(&deps.storage)?;
+    let sent = sent_funds(&env, &config, received)?;
+    let denom = wager_denom(&config, &sent, denom)?;
+    let wager = config.stakes_for_tier(&denom, &stakes)?;
+    take_wager(&mut deps.storage, &player, sent, &denom, wager, from_balance)?;
+
+    // private games are never set as the waiting game for the stakes tier
+    create_new_private_game(&mut deps.storage, &player, stakes.u8_val(), &denom, wager, invited_player, invite_hash, entropy, referrer, env.block.height)?;
+
+    let game_idx = get_current_game(&deps.storage, &player).unwrap();
+    let log = game_log(&deps.storage, "create_private_game", game_idx, None)?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Join { status: Success, game_state: Some(game_state_response) })?),
+        log,
+        data: Some(to_binary(&HandleAnswer::CreatePrivateGame { status: Success, game_state, game_state_v2 })?),
+    })
+}
+
+pub fn try_join_private_game<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    player: HumanAddr,
+    received: Option<(String, u128)>,
+    host: HumanAddr,
+    invite_code: Option<String>,
+    entropy: Option<String>,
+    entropy_commitment: Option<String>,
+    from_balance: Option<bool>,
+    denom: Option<String>,
+    referrer: Option<HumanAddr>,
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&player)?;
+    check_not_in_game(&mut deps.storage, &player, env.block.height)?;
+    let entropy = parse_player_entropy(entropy, entropy_commitment)?;
+    let referrer = parse_referrer(&deps.api, &player, referrer)?;
+
+    // the private game is the host's current game
+    let host = deps.api.canonical_address(&host)?;
+    let game_idx = get_current_game(&deps.storage, &host);
+    if game_idx.is_none() {
+        return Err(ContractError::NoPrivateGame {});
+    }
+    let game_idx = game_idx.unwrap();
+    let game_state = get_game_state(&deps.storage, game_idx)?;
+
+    if game_state.finished || game_state.player_b.is_some() || !game_state.is_private() {
+        return Err(ContractError::NoPrivateGame {});
+    }
+
+    if let Some(invited_player) = &game_state.invited_player {
+        if *invited_player != player {
+            return Err(ContractError::NotInvited {});
+        }
+    }
+    if let Some(invite_hash) = &game_state.invite_hash {
+        let matches = match invite_code {
+            Some(invite_code) => sha_256(invite_code.as_bytes()).to_vec() == *invite_hash,
+            None => false,
+        };
+        if !matches {
+            return Err(ContractError::InvalidInviteCode {});
+        }
+    }
+
+    // wager must match the host's wager, in the same denom
+    if let Some(denom) = denom {
+        if denom != game_state.denom {
+            return Err(ContractError::PrivateGameDenom { denom: game_state.denom });
+        }
+    }
+    let wager = game_state.player_a_wager.unwrap_or(0);
+    let config = get_config(&deps.storage)?;
+    let sent = sent_funds(&env, &config, received)?;
+    wager_denom(&config, &sent, Some(game_state.denom.clone()))?;
+    take_wager(&mut deps.storage, &player, sent, &game_state.denom, wager, from_balance)?;
+
+    add_second_player(&mut deps.storage, rng, game_idx, game_state, &player, wager, entropy, referrer, env.block.height)?;
+
+    let log = game_log(&deps.storage, "join_private_game", game_idx, Some("waiting".to_string()))?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::JoinPrivateGame { status: Success, game_state, game_state_v2 })?),
     })
 }
 
@@ -249,14 +805,324 @@ fn submission_provably_false(
            (hint.is_i_have() && (hint_mask & other_player_first_hint_mask > 0));
 }
 
-fn pick_extra_secret<S: Storage>(
+// decide the game from the outcomes of all rounds, equal round wins go to the reward round
+// unless more rounds were lost by both players than were tied
+fn game_outcome(round_outcomes: &[RoundOutcome]) -> RoundOutcome {
+    let count = |outcome: RoundOutcome| round_outcomes.iter().filter(|o| **o == outcome).count();
+    let player_a_wins = count(RoundOutcome::AWon);
+    let player_b_wins = count(RoundOutcome::BWon);
+    if player_a_wins > player_b_wins {
+        RoundOutcome::AWon
+    } else if player_b_wins > player_a_wins {
+        RoundOutcome::BWon
+    } else if count(RoundOutcome::BothLose) > count(RoundOutcome::Tie) {
+        RoundOutcome::BothLose
+    } else {
+        RoundOutcome::Tie
+    }
+}
+
+// what each player was sent back when the game finished, worked out from the result
+// and which insurance powerups were applied
+fn game_payouts(game_state: &GameState) -> Result<(u128, u128), ContractError> {
+    let a_wager = game_state.player_a_wager.unwrap_or(0);
+    let b_wager = game_state.player_b_wager.unwrap_or(0);
+    let payouts = match GameResult::from_u8(game_state.result.unwrap_or(GameResult::NoReward.u8_val()))? {
+        GameResult::AWon if game_state.player_b_powerup_applied => (a_wager, b_wager),
+        GameResult::AWon => (a_wager + b_wager, 0),
+        GameResult::BWon if game_state.player_a_powerup_applied => (a_wager, b_wager),
+        GameResult::BWon => (0, a_wager + b_wager),
+        GameResult::BothLose => (
+            if game_state.player_a_powerup_applied { a_wager } else { 0 },
+            if game_state.player_b_powerup_applied { b_wager } else { 0 },
+        ),
+        GameResult::AJackpotBNft => (game_state.jackpot_reward.unwrap_or(0), 0),
+        GameResult::ANftBJackpot => (0, game_state.jackpot_reward.unwrap_or(0)),
+        GameResult::NoReward => (a_wager, b_wager),
+    };
+    // less the rake taken from winning payouts
+    let a_fees = game_state.fees_paid_by(&game_state.player_a);
+    let b_fees = match &game_state.player_b {
+        Some(player_b) => game_state.fees_paid_by(player_b),
+        None => 0,
+    };
+    Ok((payouts.0 - a_fees, payouts.1 - b_fees))
+}
+
+// take the house rake from a winning payout, split it between the pool, the treasury and
+// the winner's referrer, record it in the game, and return what is left for the winner
+fn take_rake<S: Storage>(
+    storage: &mut S,
+    game_state: &mut GameState,
+    winner: &CanonicalAddr,
+    payout: u128,
+    game_idx: Option<u32>,
+    block: u64,
+) -> Result<u128, ContractError> {
+    let rake = payout * game_state.rake_bps as u128 / 10000;
+    if rake == 0 {
+        return Ok(payout);
+    }
+    // the split is fixed when the game is created, like the rake
+    let referrer = game_state.referrer_of(winner);
+    let mut to_treasury = rake * game_state.rake_treasury_bps as u128 / 10000;
+    let mut to_referral = rake * game_state.rake_referral_bps as u128 / 10000;
+    if referrer.is_none() {
+        to_treasury = to_treasury + to_referral;
+        to_referral = 0;
+    }
+    if game_state.treasury.is_none() {
+        to_treasury = 0;
+    }
+    let to_pool = rake - to_treasury - to_referral;
+
+    if let Some(treasury) = &game_state.treasury {
+        credit_balance(storage, treasury, &game_state.denom, to_treasury)?;
+    }
+    if let Some(referrer) = &referrer {
+        credit_balance(storage, referrer, &game_state.denom, to_referral)?;
+    }
+    add_to_pool(storage, &game_state.denom, to_pool, PoolChangeReason::Rake, game_idx, block)?;
+
+    game_state.fees.push(FeeRecord {
+        payer: winner.clone(),
+        amount: rake,
+        to_pool,
+        to_treasury,
+        to_referral,
+    });
+    Ok(payout - rake)
+}
+
+// update both players' stats for a game that finished with a result
+fn record_game_stats<S: Storage>(
+    storage: &mut S,
+    game_state: &mut GameState,
+) -> Result<(), ContractError> {
+    let (a_outcome, b_outcome) = match GameResult::from_u8(game_state.result.unwrap_or(GameResult::NoReward.u8_val()))? {
+        GameResult::AWon => (PlayerOutcome::Won, PlayerOutcome::Lost),
+        GameResult::BWon => (PlayerOutcome::Lost, PlayerOutcome::Won),
+        GameResult::BothLose => (PlayerOutcome::BothLose, PlayerOutcome::BothLose),
+        GameResult::AJackpotBNft => (PlayerOutcome::Jackpot, PlayerOutcome::Nft),
+        GameResult::ANftBJackpot => (PlayerOutcome::Nft, PlayerOutcome::Jackpot),
+        GameResult::NoReward if game_state.in_reward_round() => (PlayerOutcome::NoReward, PlayerOutcome::NoReward),
+        GameResult::NoReward => (PlayerOutcome::Tie, PlayerOutcome::Tie),
+    };
+    let (a_payout, b_payout) = game_payouts(game_state)?;
+    game_state.player_a_payout = Some(a_payout);
+    game_state.player_b_payout = Some(b_payout);
+    record_player_outcome(storage, &game_state.player_a, a_outcome, game_state.player_a_wager.unwrap_or(0), a_payout)?;
+    record_player_outcome(storage, &game_state.player_b.clone().unwrap(), b_outcome, game_state.player_b_wager.unwrap_or(0), b_payout)?;
+    Ok(())
+}
+
+// how a game is paid out when it finishes
+enum Settlement {
+    // played out to a result, including the picks of the reward round
+    Result(GameResult),
+    // the other player timed out, this player gets their wager back and the other wager goes to the pool
+    Timeout(CanonicalAddr),
+    // player a withdrew before anyone joined and gets their wager back
+    Withdrawn,
+    // the game was called off, the contract was stopped or nobody joined before the lobby expired.
+    // the wagers are refunded and the game has no result
+    Refunded,
+}
+
+// finish a game and pay it out: refunds, winnings and jackpots less the rake, and wagers lost to
+// the pool are credited, player stats are recorded, the wagers are released from escrow and a game
+// still waiting for an opponent leaves the lobby. The caller saves the game state
+fn settle_game<S: Storage>(
+    storage: &mut S,
+    game_idx: u32,
+    game_state: &mut GameState,
+    settlement: Settlement,
+    block: u64,
+) -> Result<(), ContractError> {
+    game_state.finished = true;
+    game_state.finished_block = Some(block);
+
+    let denom = game_state.denom.clone();
+    let player_a = game_state.player_a.clone();
+    let a_wager = game_state.player_a_wager.unwrap_or(0);
+    let b_wager = game_state.player_b_wager.unwrap_or(0);
+    let a_insured = game_state.player_a_powerup == Some(POWERUP_INSURANCE);
+    let b_insured = game_state.player_b_powerup == Some(POWERUP_INSURANCE);
+
+    match settlement {
+        Settlement::Result(result) => {
+            let player_b = game_state.player_b.clone().unwrap();
+            game_state.result = Some(result.u8_val());
+            match result {
+                GameResult::AWon | GameResult::BWon => {
+                    let (winner, loser_insured) = if result == GameResult::AWon {
+                        (player_a.clone(), b_insured)
+                    } else {
+                        (player_b.clone(), a_insured)
+                    };
+                    if loser_insured {
+                        // insurance on the losing side just refunds both wagers
+                        credit_balance(storage, &player_a, &denom, a_wager)?;
+                        credit_balance(storage, &player_b, &denom, b_wager)?;
+                        if result == GameResult::AWon {
+                            game_state.player_b_powerup_applied = true;
+                        } else {
+                            game_state.player_a_powerup_applied = true;
+                        }
+                    } else {
+                        let winnings = take_rake(storage, game_state, &winner, a_wager + b_wager, Some(game_idx), block)?;
+                        credit_balance(storage, &winner, &denom, winnings)?;
+                    }
+                },
+                GameResult::BothLose => {
+                    // insured wagers are refunded, the rest goes to the pool
+                    let mut to_pool = 0_u128;
+                    if a_insured {
+                        credit_balance(storage, &player_a, &denom, a_wager)?;
+                        game_state.player_a_powerup_applied = true;
+                    } else {
+                        to_pool = to_pool + a_wager;
+                    }
+                    if b_insured {
+                        credit_balance(storage, &player_b, &denom, b_wager)?;
+                        game_state.player_b_powerup_applied = true;
+                    } else {
+                        to_pool = to_pool + b_wager;
+                    }
+                    add_to_pool(storage, &denom, to_pool, PoolChangeReason::BothLose, Some(game_idx), block)?;
+                },
+                GameResult::AJackpotBNft | GameResult::ANftBJackpot => {
+                    let winner = if result == GameResult::AJackpotBNft { player_a.clone() } else { player_b.clone() };
+                    // jackpot is worked out from the current pool by the configured rule
+                    let jackpot = get_config(storage)?.jackpot_rule.jackpot(get_pool(storage, &denom)?);
+                    game_state.jackpot_reward = Some(jackpot);
+                    take_from_pool(storage, &denom, jackpot, PoolChangeReason::Jackpot, Some(game_idx), block)?;
+                    let jackpot = take_rake(storage, game_state, &winner, jackpot, Some(game_idx), block)?;
+                    credit_balance(storage, &winner, &denom, jackpot)?;
+                },
+                GameResult::NoReward => {
+                    credit_balance(storage, &player_a, &denom, a_wager)?;
+                    credit_balance(storage, &player_b, &denom, b_wager)?;
+                },
+            }
+            record_game_stats(storage, game_state)?;
+        },
+        Settlement::Timeout(winner) => {
+            let player_b = game_state.player_b.clone().unwrap();
+            let (winner_wager, loser, loser_wager) = if winner == player_a {
+                (a_wager, player_b, b_wager)
+            } else {
+                (b_wager, player_a, a_wager)
+            };
+            credit_balance(storage, &winner, &denom, winner_wager)?;
+            add_to_pool(storage, &denom, loser_wager, PoolChangeReason::Timeout, Some(game_idx), block)?;
+            record_player_outcome(storage, &winner, PlayerOutcome::TimeoutWon, winner_wager, winner_wager)?;
+            record_player_outcome(storage, &loser, PlayerOutcome::TimeoutLost, loser_wager, 0)?;
+            if winner == game_state.player_a {
+                game_state.player_a_payout = Some(winner_wager);
+                game_state.player_b_payout = Some(0);
+            } else {
+                game_state.player_a_payout = Some(0);
+                game_state.player_b_payout = Some(winner_wager);
+            }
+            game_state.timed_out_player = Some(loser);
+        },
+        Settlement::Withdrawn => {
+            credit_balance(storage, &player_a, &denom, a_wager)?;
+            record_player_outcome(storage, &player_a, PlayerOutcome::Withdrew, a_wager, a_wager)?;
+            game_state.player_a_payout = Some(a_wager);
+        },
+        Settlement::Refunded => {
+            credit_balance(storage, &player_a, &denom, a_wager)?;
+            game_state.player_a_payout = Some(a_wager);
+            if let Some(player_b) = game_state.player_b.clone() {
+                credit_balance(storage, &player_b, &denom, b_wager)?;
+                game_state.player_b_payout = Some(b_wager);
+            }
+        },
+    }
+
+    release_escrow(storage, game_state)?;
+    if game_state.player_b.is_none() {
+        remove_from_lobby(storage, game_state.stakes, &denom, game_idx)?;
+    }
+    Ok(())
+}
+
+// stage of a game as logged: waiting for an opponent, the stage of the current round, reward or finished
+fn game_stage(game_state: &GameState) -> Result<String, ContractError> {
+    if game_state.finished {
+        return Ok("finished".to_string());
+    }
+    if game_state.in_reward_round() {
+        return Ok("reward".to_string());
+    }
+    let round_state = match &game_state.round_state {
+        Some(round_state) if game_state.round > 0 => round_state,
+        _ => return Ok("waiting".to_string()),
+    };
+    let stage = match RoundStage::from_u8(round_state.stage)? {
+        RoundStage::Initialized => "initialized",
+        RoundStage::OnePlayerFirstSubmit => "one_player_first_submit",
+        RoundStage::BothPlayersFirstSubmit => "both_players_first_submit",
+        RoundStage::OnePlayerSecondSubmit => "one_player_second_submit",
+        RoundStage::BothPlayersSecondSubmit => "both_players_second_submit",
+        RoundStage::OnePlayerGuess => "one_player_guess",
+        RoundStage::Finished => "round_finished",
+    };
+    Ok(stage.to_string())
+}
+
+// log attributes of a game action, read from the stored game. Only what is public once the game
+// is finished is logged: never chips, hints, guesses or powerups, and results and payouts only
+// when the game has finished
+fn game_log<S: Storage>(
     storage: &S,
+    action: &str,
+    game_idx: u32,
+    from_stage: Option<String>,
+) -> Result<Vec<LogAttribute>, ContractError> {
+    let game_state = get_game_state(storage, game_idx)?;
+    let mut attributes = vec![
+        log("action", action),
+        log("game_idx", game_idx),
+        log("round", game_state.round),
+    ];
+    if let Some(from_stage) = from_stage {
+        attributes.push(log("from_stage", from_stage));
+    }
+    attributes.push(log("stage", game_stage(&game_state)?));
+
+    if game_state.finished {
+        if let Some(result) = game_state.result {
+            attributes.push(log("result", result));
+        }
+        if let Some(timed_out_player) = &game_state.timed_out_player {
+            let timed_out = if *timed_out_player == game_state.player_a { "player_a" } else { "player_b" };
+            attributes.push(log("timed_out", timed_out));
+        }
+        attributes.push(log("denom", &game_state.denom));
+        attributes.push(log("player_a_payout", game_state.player_a_payout.unwrap_or(0)));
+        attributes.push(log("player_b_payout", game_state.player_b_payout.unwrap_or(0)));
+        if let Some(jackpot) = game_state.jackpot_reward {
+            attributes.push(log("jackpot", jackpot));
+        }
+        let rake: u128 = game_state.fees.iter().map(|fee| fee.amount).sum();
+        if rake > 0 {
+            attributes.push(log("rake", rake));
+        }
+    }
+    Ok(attributes)
+}
+
+fn pick_extra_secret(
+    rng: &mut Prng,
     other_player_chip: Chip,
     other_player_hint: u8,
     prev_secret: Option<u8>,
-) -> StdResult<Option<u8>> {
+) -> Result<Option<u8>, ContractError> {
     if prev_secret.is_none() {
-        let roll = get_random_number(storage) % 3;
+        let roll = rng.next_u64() % 3;
         if roll == 0 { 
             // share opponent's color
             let color: Color = other_player_chip.color;
@@ -271,7 +1137,7 @@ fn pick_extra_secret<S: Storage>(
         }
     } else {
         let prev_secret = Hint::from_u8(prev_secret.unwrap())?;
-        let roll = get_random_number(storage) % 2;
+        let roll = rng.next_u64() % 2;
         if prev_secret.is_i_have() {
             if prev_secret.is_color() {
                 // shared opponent's color last time
@@ -313,15 +1179,16 @@ fn pick_extra_secret<S: Storage>(
 pub fn try_submit<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    rng: &mut Prng,
     target: String,
     color: Option<String>,
     shape: Option<String>,
-) -> StdResult<HandleResponse> {
+) -> Result<HandleResponse, ContractError> {
     let player = deps.api.canonical_address(&env.message.sender)?;
     debug_print(format!("Player {} submitting hint", env.message.sender));
 
     if (color.is_none() && shape.is_none()) || (color.is_some() && shape.is_some()) {
-        return Err(StdError::generic_err("Hint must be either a color or shape but not both"));
+        return Err(ContractError::HintColorAndShape {});
     }
 
     let hint: Hint;
@@ -334,7 +1201,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                     "green" => { hint = Hint::IHaveGreen },
                     "blue" => { hint = Hint::IHaveBlue },
                     "black" => { hint = Hint::IHaveBlack },
-                    _ => { return Err(StdError::generic_err("Invalid color")); },
+                    _ => { return Err(ContractError::InvalidColor {}); },
                 }
             } else { // shape
                 match shape.unwrap().as_str() {
@@ -342,7 +1209,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                     "square" => { hint = Hint::IHaveSquare },
                     "circle" => { hint = Hint::IHaveCircle },
                     "star" => { hint = Hint::IHaveStar },
-                    _ => { return Err(StdError::generic_err("Invalid shape")); },
+                    _ => { return Err(ContractError::InvalidShape {}); },
                 }
             }
         },
@@ -353,7 +1220,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                     "green" => { hint = Hint::NobodyHasGreen },
                     "blue" => { hint = Hint::NobodyHasBlue },
                     "black" => { hint = Hint::NobodyHasBlack },
-                    _ => { return Err(StdError::generic_err("Invalid color")); },
+                    _ => { return Err(ContractError::InvalidColor {}); },
                 }
             } else { // shape
                 match shape.unwrap().as_str() {
@@ -361,31 +1228,68 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                     "square" => { hint = Hint::NobodyHasSquare },
                     "circle" => { hint = Hint::NobodyHasCircle },
                     "star" => { hint = Hint::NobodyHasStar },
-                    _ => { return Err(StdError::generic_err("Invalid shape")); },
+                    _ => { return Err(ContractError::InvalidShape {}); },
                 }
             }
         },
-        _ => { return Err(StdError::generic_err("Invalid hint")); }
+        _ => { return Err(ContractError::InvalidHint {}); }
     }
 
+    let log = submit_hint(deps, env, rng, player.clone(), hint)?;
+    let game_state_response = get_game_state_response(&deps.storage, player)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::Submit { status: Success, game_state: Some(game_state_response) })?),
+    })
+}
+
+pub fn try_submit_v2<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    hint: Hint,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&env.message.sender)?;
+
+    let log = submit_hint(deps, env, rng, player.clone(), hint)?;
+    let game_state_response = get_game_state_v2_response(&deps.storage, player)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::SubmitV2 { status: Success, game_state: Some(game_state_response) })?),
+    })
+}
+
+// submit player's hint in the current round of their game, shared by v1 and v2 submit
+fn submit_hint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    player: CanonicalAddr,
+    hint: Hint,
+) -> Result<Vec<LogAttribute>, ContractError> {
     // check if already in an ongoing game
     let current_game = get_current_game(&deps.storage, &player);
     if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot submit a hint before joining a game"));
+        return Err(ContractError::NotInGame { action: "submit a hint".to_string() });
     }
 
     let mut game_state: GameState = get_game_state(&deps.storage, current_game.unwrap())?;
+    let from_stage = game_stage(&game_state)?;
 
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
 
     if game_state.round == 0 || game_state.round_state.is_none() {
-        return Err(StdError::generic_err("First round has not been initialized"));
+        return Err(ContractError::RoundNotInitialized {});
     }
 
-    if game_state.round >= 3 {
-        return Err(StdError::generic_err("Finished round with submissions"))
+    if game_state.round > game_state.rounds_per_game {
+        return Err(ContractError::RoundsFinished { moves: "submissions".to_string() })
     }
 
     let mut round_state: RoundState = game_state.round_state.unwrap();
@@ -405,7 +1309,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // calculate secret to give out
                     round_state.player_b_first_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_a_chip.to_humanized()?,
                         round_state.player_a_first_hint, 
                         None
@@ -419,14 +1323,14 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // calculate secret to give out
                     round_state.player_a_first_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_b_chip.to_humanized()?,
                         round_state.player_b_first_hint, 
                         None
                     )?;
                 }
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::OnePlayerFirstSubmit.u8_val();
 
@@ -444,7 +1348,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // calculate secret to give out
                     round_state.player_b_first_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_a_chip.to_humanized()?,
                         round_state.player_a_first_hint, 
                         None
@@ -458,14 +1362,14 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // calculate secret to give out
                     round_state.player_a_first_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_b_chip.to_humanized()?,
                         round_state.player_b_first_hint, 
                         None
                     )?;
                 }
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::BothPlayersFirstSubmit.u8_val();
 
@@ -480,10 +1384,10 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if (first_hint.is_i_have() && hint.is_i_have()) || 
                    (first_hint.is_nobody_has() && hint.is_nobody_has())
                 {
-                    return Err(StdError::generic_err("Assertions must have different targets: i_have and nobody_has"));
+                    return Err(ContractError::SameAssertionTarget {});
                 }
                 if first_hint.to_bitmask() == hint.to_bitmask() {
-                    return Err(StdError::generic_err("Second assertion cannot contradict first assertion"));
+                    return Err(ContractError::ContradictingAssertion {});
                 }
                 round_state.player_a_second_submit = new_hint;
                 round_state.player_a_second_submit_block = Some(env.block.height);
@@ -492,7 +1396,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // check if a secret was revealed in the first submission, and pick accordingly
                     round_state.player_b_second_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_a_chip.to_humanized()?,
                         round_state.player_a_first_hint, 
                         round_state.player_b_first_extra_secret,
@@ -503,10 +1407,10 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if (first_hint.is_i_have() && hint.is_i_have()) || 
                    (first_hint.is_nobody_has() && hint.is_nobody_has())
                 {
-                    return Err(StdError::generic_err("Assertions must have different targets: i_have and nobody_has"));
+                    return Err(ContractError::SameAssertionTarget {});
                 }
                 if first_hint.to_bitmask() == hint.to_bitmask() {
-                    return Err(StdError::generic_err("Second assertion cannot contradict first assertion"));
+                    return Err(ContractError::ContradictingAssertion {});
                 }
                 round_state.player_b_second_submit = new_hint;
                 round_state.player_b_second_submit_block = Some(env.block.height);
@@ -515,14 +1419,14 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // check if a secret was revealed in the first submission, and pick accordingly
                     round_state.player_a_second_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_b_chip.to_humanized()?,
                         round_state.player_b_first_hint, 
                         round_state.player_a_first_extra_secret,
                     )?;
                 }
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::OnePlayerSecondSubmit.u8_val();
 
@@ -537,10 +1441,10 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if (first_hint.is_i_have() && hint.is_i_have()) || 
                    (first_hint.is_nobody_has() && hint.is_nobody_has())
                 {
-                    return Err(StdError::generic_err("Assertions must have different targets: i_have and nobody_has"));
+                    return Err(ContractError::SameAssertionTarget {});
                 }
                 if first_hint.to_bitmask() == hint.to_bitmask() {
-                    return Err(StdError::generic_err("Second assertion cannot contradict first assertion"));
+                    return Err(ContractError::ContradictingAssertion {});
                 }
                 round_state.player_a_second_submit = new_hint;
                 round_state.player_a_second_submit_block = Some(env.block.height);
@@ -549,7 +1453,7 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // check if a secret was revealed in the first submission, and pick accordingly
                     round_state.player_b_second_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_a_chip.to_humanized()?,
                         round_state.player_a_first_hint, 
                         round_state.player_b_first_extra_secret,
@@ -560,10 +1464,10 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if (first_hint.is_i_have() && hint.is_i_have()) || 
                    (first_hint.is_nobody_has() && hint.is_nobody_has())
                 {
-                    return Err(StdError::generic_err("Assertions must have different targets: i_have and nobody_has"));
+                    return Err(ContractError::SameAssertionTarget {});
                 }
                 if first_hint.to_bitmask() == hint.to_bitmask() {
-                    return Err(StdError::generic_err("Second assertion cannot contradict first assertion"));
+                    return Err(ContractError::ContradictingAssertion {});
                 }
                 round_state.player_b_second_submit = new_hint;
                 round_state.player_b_second_submit_block = Some(env.block.height);
@@ -572,41 +1476,35 @@ pub fn try_submit<S: Storage, A: Api, Q: Querier>(
                 if submission_provably_false(hint, other_player_chip, other_player_first_hint) {
                     // check if a secret was revealed in the first submission, and pick accordingly
                     round_state.player_a_second_extra_secret = pick_extra_secret(
-                        &deps.storage, 
+                        rng, 
                         round_state.player_b_chip.to_humanized()?,
                         round_state.player_b_first_hint, 
                         round_state.player_a_first_extra_secret,
                     )?;
                 }
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::BothPlayersSecondSubmit.u8_val();
 
             game_state.round_state = Some(round_state);
             update_game_state(&mut deps.storage, current_game.unwrap(), &game_state)?;
         },
-        _ => { return Err(StdError::generic_err("Not a submission round")); },
+        _ => { return Err(ContractError::NotSubmissionRound {}); },
     };
 
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Submit { status: Success, game_state: Some(game_state_response) })?),
-    })
+    game_log(&deps.storage, "submit", current_game.unwrap(), Some(from_stage))
 }
 
 pub fn try_guess<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    rng: &mut Prng,
     target: String,
     color: Option<String>,
     shape: Option<String>,
-) -> StdResult<HandleResponse> {
+) -> Result<HandleResponse, ContractError> {
     let player = deps.api.canonical_address(&env.message.sender)?;
-    let mut messages: Vec<CosmosMsg> = vec![];
 
     let guess: Guess;
 
@@ -618,14 +1516,14 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
         };
     } else {
         if color.is_none() || shape.is_none() {
-            return Err(StdError::generic_err("Invalid guess"));
+            return Err(ContractError::InvalidGuess {});
         }
         let color_type: Color = match color.unwrap().as_str() {
             "red" => Color::Red,
             "green" => Color::Green,
             "blue" => Color::Blue,
             "black" => Color::Black,
-            _ => { return Err(StdError::generic_err("Invalid color")); }
+            _ => { return Err(ContractError::InvalidColor {}); }
         };
     
         let shape_type: Shape = match shape.unwrap().as_str() {
@@ -633,7 +1531,7 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
             "square" => Shape::Square,
             "circle" => Shape::Circle,
             "star" => Shape::Star,
-            _ => { return Err(StdError::generic_err("Invalid shape")); }
+            _ => { return Err(ContractError::InvalidShape {}); }
         };
         match target.as_str() {
             "bag" => {
@@ -650,28 +1548,74 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
                     shape: Some(shape_type),
                 };
             },
-            _ => { return Err(StdError::generic_err("Invalid guess")); }
+            _ => { return Err(ContractError::InvalidGuess {}); }
         }
     }
 
-    // check if already in an ongoing game
-    let current_game = get_current_game(&deps.storage, &player);
-    if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot submit a guess before joining a game"));
+    let log = make_guess(deps, env, rng, player.clone(), guess)?;
+    let game_state_response = get_game_state_response(&deps.storage, player)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::Guess { status: Success, game_state: Some(game_state_response) })?),
+    })
+}
+
+pub fn try_guess_v2<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    guess: Guess,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&env.message.sender)?;
+
+    // abstaining has no color or shape, a guess at the bag or the opponent's chip has both
+    let valid = match guess.target {
+        Target::Abstain => guess.color.is_none() && guess.shape.is_none(),
+        Target::Bag | Target::Opponent => guess.color.is_some() && guess.shape.is_some(),
+    };
+    if !valid {
+        return Err(ContractError::InvalidGuess {});
+    }
+
+    let log = make_guess(deps, env, rng, player.clone(), guess)?;
+    let game_state_response = get_game_state_v2_response(&deps.storage, player)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::GuessV2 { status: Success, game_state: Some(game_state_response) })?),
+    })
+}
+
+// make player's guess in the current round of their game, shared by v1 and v2 guess
+fn make_guess<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    player: CanonicalAddr,
+    guess: Guess,
+) -> Result<Vec<LogAttribute>, ContractError> {
+    // check if already in an ongoing game
+    let current_game = get_current_game(&deps.storage, &player);
+    if current_game.is_none() {
+        return Err(ContractError::NotInGame { action: "submit a guess".to_string() });
     }
 
     let mut game_state: GameState = get_game_state(&deps.storage, current_game.unwrap())?;
+    let from_stage = game_stage(&game_state)?;
 
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
 
     if game_state.round == 0 || game_state.round_state.is_none() {
-        return Err(StdError::generic_err("First round has not been initialized"));
+        return Err(ContractError::RoundNotInitialized {});
     }
 
-    if game_state.round >= 3 {
-        return Err(StdError::generic_err("Finished round with guesses"))
+    if game_state.round > game_state.rounds_per_game {
+        return Err(ContractError::RoundsFinished { moves: "guesses".to_string() })
     }
 
     let mut round_state: RoundState = game_state.round_state.unwrap();
@@ -687,7 +1631,7 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
                 round_state.player_b_guess = new_guess;
                 round_state.player_b_guess_block = Some(env.block.height);
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::OnePlayerGuess.u8_val();
 
@@ -734,7 +1678,7 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
                 round_state.player_b_guess = new_guess;
                 round_state.player_b_guess_block = Some(env.block.height);
             } else {
-                return Err(StdError::generic_err("Cannot accept a submission from player"));
+                return Err(ContractError::MoveNotAccepted {});
             }
             round_state.stage = RoundStage::Finished.u8_val();
 
@@ -770,223 +1714,28 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
 
             game_state.round_state = Some(round_state.clone());
 
-            // now only one round
-            // check if it goes to pick reward round
-            let player_a_round_result = RoundResult::from_u8(round_state.player_a_round_result.unwrap())?;
-            let player_b_round_result = RoundResult::from_u8(round_state.player_b_round_result.unwrap())?;
-            if (player_a_round_result == RoundResult::BagCorrect && player_b_round_result == RoundResult::BagCorrect) ||
-               (player_a_round_result == RoundResult::OpponentCorrect && player_b_round_result == RoundResult::OpponentCorrect) ||
-               (player_a_round_result == RoundResult::Abstain && player_b_round_result == RoundResult::Abstain) {
-                // advance to the pick reward round
-                game_state.round = 3;
+            if game_state.round < game_state.rounds_per_game {
+                // more rounds to play, deal a fresh round
+                game_state.round_history.push(round_state);
+                game_state.round_state = Some(create_new_round(rng, env.block.height, &game_state.chip_weights, &game_state.players_entropy())?);
+                game_state.round = game_state.round + 1;
             } else {
-                // game does not go to pick reward round, so it is finished
-                game_state.finished = true;
-
-                // check winners and losers
-                if (
-                        player_a_round_result == RoundResult::BagCorrect && (
-                        player_b_round_result == RoundResult::BagWrong || 
-                        player_b_round_result == RoundResult::OpponentWrong || 
-                        player_b_round_result == RoundResult::Abstain)
-                    ) || (
-                        player_a_round_result == RoundResult::OpponentCorrect && (
-                        player_b_round_result == RoundResult::BagCorrect ||
-                        player_b_round_result == RoundResult::BagWrong ||
-                        player_b_round_result == RoundResult::OpponentWrong ||
-                        player_b_round_result == RoundResult::Abstain)
-                    ) || (
-                        player_a_round_result == RoundResult::Abstain && (
-                        player_b_round_result == RoundResult::BagWrong ||
-                        player_b_round_result == RoundResult::OpponentWrong)
-                   ) 
-                {
-                    // Player A WINS
-                    game_state.result = Some(GameResult::AWon.u8_val());
-
-                    // Check if B has applied Insurance powerup
-                    if game_state.player_b_powerup.is_some() && game_state.player_b_powerup.unwrap() == POWERUP_INSURANCE {
-                        // Yes, just refund wagers to both players
-                        let a_refund = game_state.player_a_wager.unwrap_or(0);
-                        if a_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address.clone(),
-                                to_address: deps.api.human_address(&game_state.player_a)?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(a_refund),
-                                }],
-                            }));
-                        }
-                        let b_refund = game_state.player_b_wager.unwrap_or(0);
-                        if b_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(b_refund),
-                                }],
-                            }));
-                        }
-
-                        game_state.player_b_powerup_applied = true;
-                    } else {
-                        // No, give winnings to player A
-                        let winnings = game_state.player_a_wager.unwrap_or(0) + game_state.player_b_wager.unwrap_or(0);
-                        if winnings > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_a)?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(winnings),
-                                }],
-                            }));
-                        }
-                    }
-                } else if (
-                        player_b_round_result == RoundResult::BagCorrect && (
-                        player_a_round_result == RoundResult::BagWrong || 
-                        player_a_round_result == RoundResult::OpponentWrong || 
-                        player_a_round_result == RoundResult::Abstain)
-                    ) || (
-                        player_b_round_result == RoundResult::OpponentCorrect && (
-                        player_a_round_result == RoundResult::BagCorrect ||
-                        player_a_round_result == RoundResult::BagWrong ||
-                        player_a_round_result == RoundResult::OpponentWrong ||
-                        player_a_round_result == RoundResult::Abstain)
-                    ) || (
-                        player_b_round_result == RoundResult::Abstain && (
-                        player_a_round_result == RoundResult::BagWrong ||
-                        player_a_round_result == RoundResult::OpponentWrong)
-                    ) 
-                {
-                    // Player B WINS
-                    game_state.result = Some(GameResult::BWon.u8_val());
-
-                    // Check if A has applied Insurance powerup
-                    if game_state.player_a_powerup.is_some() && game_state.player_a_powerup.unwrap() == POWERUP_INSURANCE {
-                        // Yes, just refund wagers to both players
-                        let a_refund = game_state.player_a_wager.unwrap_or(0);
-                        if a_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address.clone(),
-                                to_address: deps.api.human_address(&game_state.player_a)?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(a_refund),
-                                }],
-                            }));
-                        }
-                        let b_refund = game_state.player_b_wager.unwrap_or(0);
-                        if b_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(b_refund),
-                                }],
-                            }));
-                        }
-
-                        game_state.player_a_powerup_applied = true;
-                    } else {
-                        let winnings = game_state.player_a_wager.unwrap_or(0) + game_state.player_b_wager.unwrap_or(0);
-                        if winnings > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(winnings),
-                                }],
-                            }));
-                        }
-                    }
-                } else if (
-                        player_a_round_result == RoundResult::BagWrong && (
-                        player_b_round_result == RoundResult::BagWrong || 
-                        player_b_round_result == RoundResult::OpponentWrong)
-                    ) || (
-                        player_a_round_result == RoundResult::OpponentWrong && (
-                        player_b_round_result == RoundResult::BagWrong ||
-                        player_b_round_result == RoundResult::OpponentWrong)
-                    )
-                {
-                    // Both LOSE
-                    game_state.result = Some(GameResult::BothLose.u8_val());
-                    let mut pool = get_pool(&deps.storage)?;
-                    let player_a_insurance: bool = game_state.player_a_powerup.is_some() && game_state.player_a_powerup.unwrap() == POWERUP_INSURANCE;
-                    let player_b_insurance: bool = game_state.player_b_powerup.is_some() && game_state.player_b_powerup.unwrap() == POWERUP_INSURANCE;
-
-                    // Check if players have applied Insurance powerup
-                    if player_a_insurance && player_b_insurance {
-                        // refund both players
-                        let a_refund = game_state.player_a_wager.unwrap_or(0);
-                        if a_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address.clone(),
-                                to_address: deps.api.human_address(&game_state.player_a)?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(a_refund),
-                                }],
-                            }));
-                        }
-                        game_state.player_a_powerup_applied = true;
-                        
-                        let b_refund = game_state.player_b_wager.unwrap_or(0);
-                        if b_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(b_refund),
-                                }],
-                            }));
-                        }
-                        game_state.player_b_powerup_applied = true;
-                    } else if player_a_insurance && !player_b_insurance {
-                        // refund player A, send B's wager to the pool
-                        let a_refund = game_state.player_a_wager.unwrap_or(0);
-                        if a_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address.clone(),
-                                to_address: deps.api.human_address(&game_state.player_a)?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(a_refund),
-                                }],
-                            }));
-                        }
-                        game_state.player_a_powerup_applied = true;
-                        pool = pool + game_state.player_b_wager.unwrap_or(0);
-                    } else if !player_a_insurance && player_b_insurance {
-                        // refund player B, send A's wager to the pool
-                        let b_refund = game_state.player_b_wager.unwrap_or(0);
-                        if b_refund > 0 {
-                            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                                from_address: env.contract.address,
-                                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                                amount: vec![Coin {
-                                    denom: DENOM.to_string(),
-                                    amount: Uint128(b_refund),
-                                }],
-                            }));
-                        }
-                        game_state.player_b_powerup_applied = true;
-                        pool = pool + game_state.player_a_wager.unwrap_or(0);
-                    } else {
-                        // no Insurance applied, record the increase in the pool
-                        pool = pool + game_state.player_a_wager.unwrap_or(0) + game_state.player_b_wager.unwrap_or(0);
-                    }
-
-                    if !(player_a_insurance && player_b_insurance) {
-                        set_pool(&mut deps.storage, pool)?;
-                    }
+                // last round, score round wins to check if it goes to pick reward round
+                let outcome = game_outcome(&game_state.round_outcomes()?);
+                if outcome == RoundOutcome::Tie && game_state.stakes == StakesTier::Practice.u8_val() {
+                    // practice games have no reward round
+                    settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Result(GameResult::NoReward), env.block.height)?;
+                } else if outcome == RoundOutcome::Tie {
+                    // advance to the pick reward round
+                    game_state.round = game_state.reward_round();
+                } else {
+                    // game does not go to pick reward round, so it is finished
+                    let result = match outcome {
+                        RoundOutcome::AWon => GameResult::AWon,
+                        RoundOutcome::BWon => GameResult::BWon,
+                        _ => GameResult::BothLose,
+                    };
+                    settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Result(result), env.block.height)?;
                 }
             }
 
@@ -998,48 +1747,45 @@ pub fn try_guess<S: Storage, A: Api, Q: Querier>(
 
             update_game_state(&mut deps.storage, current_game.unwrap(), &game_state)?;
         },
-        _ => { return Err(StdError::generic_err("Not a guess round")); }
+        _ => { return Err(ContractError::NotGuessRound {}); }
     }
     
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
-
-    Ok(HandleResponse {
-        messages,
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Guess { status: Success, game_state: Some(game_state_response) })?),
-    })
+    game_log(&deps.storage, "guess", current_game.unwrap(), Some(from_stage))
 }
 
 pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    rng: &mut Prng,
     reward: String,
-) -> StdResult<HandleResponse> {
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
     let player = deps.api.canonical_address(&env.message.sender)?;
     let mut messages: Vec<CosmosMsg> = vec![];
 
     if reward != "nft" && reward != "jackpot" {
-        return Err(StdError::generic_err("Invalid reward selection"));
+        return Err(ContractError::InvalidReward {});
     }
 
     // check if already in an ongoing game
     let current_game = get_current_game(&deps.storage, &player);
     if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot pick a reward before joining a game"));
+        return Err(ContractError::NotInGame { action: "pick a reward".to_string() });
     }
     
     let mut game_state: GameState = get_game_state(&deps.storage, current_game.unwrap())?;
+    let from_stage = game_stage(&game_state)?;
     
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
     
     if game_state.round == 0 || game_state.round_state.is_none() {
-        return Err(StdError::generic_err("First round has not been initialized"));
+        return Err(ContractError::RoundNotInitialized {});
     }
     
-    if game_state.round < 3 {
-        return Err(StdError::generic_err("Reward round has not started"))
+    if !game_state.in_reward_round() {
+        return Err(ContractError::RewardRoundNotStarted {})
     }
 
     // check if other player has picked reward
@@ -1073,23 +1819,7 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
         if player_a_reward_pick == player_b_reward_pick {
             // both picked the same reward
             // refund wagers
-            game_state.result = Some(GameResult::NoReward.u8_val());
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                from_address: env.contract.address.clone(),
-                to_address: deps.api.human_address(&game_state.player_a)?,
-                amount: vec![Coin {
-                    denom: DENOM.to_string(),
-                    amount: Uint128(game_state.player_a_wager.unwrap_or(0)),
-                }],
-            }));
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-                from_address: env.contract.address,
-                to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                amount: vec![Coin {
-                    denom: DENOM.to_string(),
-                    amount: Uint128(game_state.player_b_wager.unwrap_or(0)),
-                }],
-            }));
+            settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Result(GameResult::NoReward), env.block.height)?;
         } else {
             // give out rewards
 
@@ -1097,7 +1827,7 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
             let token_id = Some(format!("game-badge-{}", current_game.unwrap()));
             game_state.nft_token_id = token_id.clone();
             let name = format!("prisnr.games");
-            let random_bytes: [u8; 8] = get_random_number(&deps.storage).to_be_bytes();
+            let random_bytes: [u8; 8] = rng.next_u64().to_be_bytes();
             let rgb = format!("{:x?}{:x?}{:x?}", random_bytes[0], random_bytes[1], random_bytes[2]);
             let random_url = format!(
                 "{:x?}{:x?}{:x?}{:x?}{:x?}{:x?}{:x?}{:x?}", 
@@ -1109,7 +1839,7 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
             // description indicates any powerup feature
             // "simple" = no puwerup feature (90% of all badges)
             // "insurance" = Insurance powerup
-            let roll = get_random_number(&deps.storage) % 100;
+            let roll = rng.next_u64() % 100;
             let description: String;
             if roll < 10 {
                 description = "insurance".to_string();
@@ -1153,22 +1883,8 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
             });
             let minter: ContractInfo = get_minter(&deps.storage)?.to_humanized(&deps.api)?;
 
-            // jackpot is equal to half of the current pool
-            let current_pool = get_pool(&deps.storage)?;
-            let jackpot = current_pool / 2;
-            game_state.jackpot_reward = Some(jackpot);
             if player_a_reward_pick == REWARD_POOL {
-                game_state.result = Some(GameResult::AJackpotBNft.u8_val());
-                if jackpot > 0 {
-                    messages.push(CosmosMsg::Bank(BankMsg::Send {
-                        from_address: env.contract.address.clone(),
-                        to_address: deps.api.human_address(&game_state.player_a)?,
-                        amount: vec![Coin {
-                            denom: DENOM.to_string(),
-                            amount: Uint128(jackpot),
-                        }],
-                    }));
-                }
+                settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Result(GameResult::AJackpotBNft), env.block.height)?;
 
                 // mint and send NFT to player b
                 let nft_owner: Option<HumanAddr> = Some(deps.api.human_address(&game_state.player_b.clone().unwrap())?);
@@ -1185,17 +1901,8 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
                 )?;
                 messages.push(cosmos_msg);
             } else { // player b picked pool
-                game_state.result = Some(GameResult::ANftBJackpot.u8_val());
-                if jackpot > 0 {
-                    messages.push(CosmosMsg::Bank(BankMsg::Send {
-                        from_address: env.contract.address.clone(),
-                        to_address: deps.api.human_address(&game_state.player_b.clone().unwrap())?,
-                        amount: vec![Coin {
-                            denom: DENOM.to_string(),
-                            amount: Uint128(jackpot),
-                        }],
-                    }));
-                }
+                settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Result(GameResult::ANftBJackpot), env.block.height)?;
+
                 // mint and send NFT to player a
                 let nft_owner: Option<HumanAddr> = Some(deps.api.human_address(&game_state.player_a)?);
                 let cosmos_msg = mint_nft_msg(
@@ -1211,403 +1918,215 @@ pub fn try_pick_reward<S: Storage, A: Api, Q: Querier>(
                 )?;
                 messages.push(cosmos_msg);
             }
-            set_pool(&mut deps.storage, current_pool - jackpot)?;
         }
-        game_state.finished = true;
     }
 
     update_game_state(&mut deps.storage, current_game.unwrap(), &game_state)?;
 
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
+    let log = game_log(&deps.storage, "pick_reward", current_game.unwrap(), Some(from_stage))?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
 
     Ok(HandleResponse {
         messages,
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::PickReward { status: Success, game_state: Some(game_state_response) })?),
+        log,
+        data: Some(to_binary(&HandleAnswer::PickReward { status: Success, game_state, game_state_v2 })?),
     })
 }
 
 pub fn try_withdraw<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-) -> StdResult<HandleResponse> {
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
     let player = deps.api.canonical_address(&env.message.sender)?;
-    let mut messages: Vec<CosmosMsg> = vec![];
 
     // check if already in an ongoing game
     let current_game = get_current_game(&deps.storage, &player);
     if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot withdraw before joining a game"));
+        return Err(ContractError::NotInGame { action: "withdraw".to_string() });
     }
         
     let mut game_state: GameState = get_game_state(&deps.storage, current_game.unwrap())?;
+    let from_stage = game_stage(&game_state)?;
 
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
     
     if game_state.round > 0 || game_state.round_state.is_some() {
-        return Err(StdError::generic_err("Cannot withdraw once another player has joined game"));
+        return Err(ContractError::OpponentJoined {});
     }
     
-    game_state.finished = true;
+    // refund the wager, the game is no longer waiting for a second player
+    settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Withdrawn, env.block.height)?;
     update_game_state(&mut deps.storage, current_game.unwrap(), &game_state)?;
 
-    if player == game_state.player_a && game_state.player_a_wager.unwrap_or(0) > 0 {
-        // refund wager
-        messages.push(CosmosMsg::Bank(BankMsg::Send {
-            from_address: env.contract.address.clone(),
-            to_address: deps.api.human_address(&player)?,
-            amount: vec![Coin {
-                denom: DENOM.to_string(),
-                amount: Uint128(game_state.player_a_wager.unwrap_or(0)),
-            }],
-        }));
+    let log = game_log(&deps.storage, "withdraw", current_game.unwrap(), Some(from_stage))?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::Withdraw { status: Success, game_state, game_state_v2 })?),
+    })
+}
+
+pub fn try_claim_refund<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&env.message.sender)?;
+
+    if get_config(&deps.storage)?.status != ContractStatus::StopAll.to_u8() {
+        return Err(ContractError::RefundsUnavailable {});
     }
 
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
+    let current_game = get_current_game(&deps.storage, &player);
+    if current_game.is_none() {
+        return Err(ContractError::NoGame {});
+    }
+    let game_idx = current_game.unwrap();
+
+    let mut game_state: GameState = get_game_state(&deps.storage, game_idx)?;
+    let from_stage = game_stage(&game_state)?;
+
+    if game_state.finished {
+        return Err(ContractError::NothingToRefund {});
+    }
+
+    // wagers stay escrowed until the game is finished, so end the game and give both back,
+    // that way the game cannot be resumed half refunded once the contract is running again.
+    // result is left unset as the game was not played out
+    settle_game(&mut deps.storage, game_idx, &mut game_state, Settlement::Refunded, env.block.height)?;
+    update_game_state(&mut deps.storage, game_idx, &game_state)?;
+
+    let log = game_log(&deps.storage, "claim_refund", game_idx, Some(from_stage))?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
 
     Ok(HandleResponse {
-        messages,
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Withdraw { status: Success, game_state: Some(game_state_response), })?),
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::ClaimRefund { status: Success, game_state, game_state_v2 })?),
     })
 }
 
 pub fn try_force_endgame<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-) -> StdResult<HandleResponse> {
+    v2: Option<bool>,
+) -> Result<HandleResponse, ContractError> {
     let player = deps.api.canonical_address(&env.message.sender)?;
-    let mut messages: Vec<CosmosMsg> = vec![];
 
     // check if already in an ongoing game
     let current_game = get_current_game(&deps.storage, &player);
     if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot force endgame before joining a game"));
+        return Err(ContractError::NotInGame { action: "force endgame".to_string() });
     }
         
     let mut game_state: GameState = get_game_state(&deps.storage, current_game.unwrap())?;
+    let from_stage = game_stage(&game_state)?;
 
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
     
     if game_state.round == 0 || game_state.round_state.is_none() {
-        return Err(StdError::generic_err("Cannot force endgame until another player has joined game"));
+        return Err(ContractError::NoOpponent {});
     }
 
-    let config = get_config(&deps.storage)?;
+    // use the timeout the game was created with
+    let timeout = game_state.timeout;
+    let is_player_a = player == game_state.player_a;
 
     // check if other player has timed out
-    if game_state.round < 3 {
+    if game_state.round <= game_state.rounds_per_game {
         // in assertion/guessing round
         let round_state = game_state.clone().round_state.unwrap();
         match RoundStage::from_u8(round_state.stage)? {
             RoundStage::Initialized => {
-                if env.block.height < round_state.round_start_block + config.timeout {
-                    return Err(StdError::generic_err("Opponent has not timed out"));
-                }
                 // both players at risk of timeout
-                if player == game_state.player_a {
-                    // force endgame with b as timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;
-                } else if player == game_state.clone().player_b.unwrap() {
-                    // force endgame with a as timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?;
+                if env.block.height < round_state.round_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             RoundStage::OnePlayerFirstSubmit => {
-                if player == game_state.player_a {
-                    if round_state.player_b_first_submit_block.is_some() || env.block.height < round_state.round_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player b has timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;                    
-                } else if player == game_state.clone().player_b.unwrap() {
-                    if round_state.player_a_first_submit_block.is_some() || env.block.height < round_state.round_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player a has timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?; 
+                let opponent_submitted = if is_player_a {
+                    round_state.player_b_first_submit_block.is_some()
+                } else {
+                    round_state.player_a_first_submit_block.is_some()
+                };
+                if opponent_submitted || env.block.height < round_state.round_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             RoundStage::BothPlayersFirstSubmit => {
-                let second_submit_turn_start_block = max(round_state.player_a_first_submit_block.unwrap(), round_state.player_b_first_submit_block.unwrap());
-                if env.block.height < second_submit_turn_start_block + config.timeout {
-                    return Err(StdError::generic_err("Opponent has not timed out"));
-                }
                 // both players at risk of timeout
-                if player == game_state.player_a {
-                    // force endgame with b as timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;
-                } else if player == game_state.clone().player_b.unwrap() {
-                    // force endgame with a as timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?;
+                let second_submit_turn_start_block = max(round_state.player_a_first_submit_block.unwrap(), round_state.player_b_first_submit_block.unwrap());
+                if env.block.height < second_submit_turn_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             RoundStage::OnePlayerSecondSubmit => {
                 let second_submit_turn_start_block = max(round_state.player_a_first_submit_block.unwrap(), round_state.player_b_first_submit_block.unwrap());
-                if player == game_state.player_a {
-                    if round_state.player_b_second_submit_block.is_some() || env.block.height < second_submit_turn_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player b has timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;                    
-                } else if player == game_state.clone().player_b.unwrap() {
-                    if round_state.player_a_second_submit_block.is_some() || env.block.height < second_submit_turn_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player a has timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?; 
+                let opponent_submitted = if is_player_a {
+                    round_state.player_b_second_submit_block.is_some()
+                } else {
+                    round_state.player_a_second_submit_block.is_some()
+                };
+                if opponent_submitted || env.block.height < second_submit_turn_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             RoundStage::BothPlayersSecondSubmit => {
-                let guess_turn_start_block = max(round_state.player_a_second_submit_block.unwrap(), round_state.player_b_second_submit_block.unwrap());
-                if env.block.height < guess_turn_start_block + config.timeout {
-                    return Err(StdError::generic_err("Opponent has not timed out"));
-                }
                 // both players at risk of timeout
-                if player == game_state.player_a {
-                    // force endgame with b as timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;
-                } else if player == game_state.clone().player_b.unwrap() {
-                    // force endgame with a as timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?;
+                let guess_turn_start_block = max(round_state.player_a_second_submit_block.unwrap(), round_state.player_b_second_submit_block.unwrap());
+                if env.block.height < guess_turn_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             RoundStage::OnePlayerGuess => {
                 let guess_turn_start_block = max(round_state.player_a_second_submit_block.unwrap(), round_state.player_b_second_submit_block.unwrap());
-                if player == game_state.player_a {
-                    if round_state.player_b_guess_block.is_some() || env.block.height < guess_turn_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player b has timed out
-                    // refund player a wager
-                    if game_state.player_a_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_a_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player b wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?;                    
-                } else if player == game_state.clone().player_b.unwrap() {
-                    if round_state.player_a_guess_block.is_some() || env.block.height < guess_turn_start_block + config.timeout {
-                        return Err(StdError::generic_err("Opponent has not timed out"));
-                    }
-                    // player a has timed out
-                    // refund player b wager
-                    if game_state.player_b_wager.unwrap_or(0) > 0 {
-                        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                            from_address: env.contract.address.clone(),
-                            to_address: deps.api.human_address(&player)?,
-                            amount: vec![Coin {
-                                denom: DENOM.to_string(),
-                                amount: Uint128(game_state.player_b_wager.unwrap()),
-                            }],
-                        }));
-                    }
-                    // send player a wager to pool
-                    let current_pool = get_pool(&deps.storage)?;
-                    set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?; 
+                let opponent_guessed = if is_player_a {
+                    round_state.player_b_guess_block.is_some()
+                } else {
+                    round_state.player_a_guess_block.is_some()
+                };
+                if opponent_guessed || env.block.height < guess_turn_start_block + timeout {
+                    return Err(ContractError::OpponentNotTimedOut {});
                 }
             },
             _ => { 
                 // if round is finished and not round 3 then game is finished 
             }
         }
-    } else if game_state.round == 3 {
+    } else if game_state.in_reward_round() {
         // in pick reward round
         let round_state = game_state.clone().round_state.unwrap();
         let pick_reward_round_start_block = max(round_state.player_a_guess_block.unwrap(), round_state.player_b_guess_block.unwrap());
-        if player == game_state.player_a {
-            if game_state.player_b_reward_pick_block.is_some() || env.block.height < pick_reward_round_start_block + config.timeout {
-                return Err(StdError::generic_err("Opponent has not timed out"));
-            }
-            // player b has timed out
-            // refund player a wager
-            if game_state.player_a_wager.unwrap_or(0) > 0 {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    from_address: env.contract.address.clone(),
-                    to_address: deps.api.human_address(&player)?,
-                    amount: vec![Coin {
-                        denom: DENOM.to_string(),
-                        amount: Uint128(game_state.player_a_wager.unwrap()),
-                    }],
-                }));
-            }
-            // send player b wager to pool
-            let current_pool = get_pool(&deps.storage)?;
-            set_pool(&mut deps.storage, current_pool + game_state.player_b_wager.unwrap_or(0))?; 
-        } else if player == game_state.clone().player_b.unwrap() {
-            if game_state.player_a_reward_pick_block.is_some() || env.block.height < pick_reward_round_start_block + config.timeout {
-                return Err(StdError::generic_err("Opponent has not timed out"));
-            }
-            // player a has timed out
-            // refund player b wager
-            if game_state.player_b_wager.unwrap_or(0) > 0 {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    from_address: env.contract.address.clone(),
-                    to_address: deps.api.human_address(&player)?,
-                    amount: vec![Coin {
-                        denom: DENOM.to_string(),
-                        amount: Uint128(game_state.player_b_wager.unwrap()),
-                    }],
-                }));
-            }
-            // send player a wager to pool
-            let current_pool = get_pool(&deps.storage)?;
-            set_pool(&mut deps.storage, current_pool + game_state.player_a_wager.unwrap_or(0))?; 
+        let opponent_picked = if is_player_a {
+            game_state.player_b_reward_pick_block.is_some()
+        } else {
+            game_state.player_a_reward_pick_block.is_some()
+        };
+        if opponent_picked || env.block.height < pick_reward_round_start_block + timeout {
+            return Err(ContractError::OpponentNotTimedOut {});
         }
     } else {
-        return Err(StdError::generic_err("Invalid round error"));
+        return Err(ContractError::InvalidRound {});
     }
-    
-    game_state.finished = true;
+
+    // player forcing the endgame gets their wager back, opponent's wager goes to the pool
+    settle_game(&mut deps.storage, current_game.unwrap(), &mut game_state, Settlement::Timeout(player.clone()), env.block.height)?;
     update_game_state(&mut deps.storage, current_game.unwrap(), &game_state)?;
 
-    let game_state_response = get_game_state_response(&deps.storage, player)?;
+    let log = game_log(&deps.storage, "force_endgame", current_game.unwrap(), Some(from_stage))?;
+    let (game_state, game_state_v2) = game_state_answer(&deps.storage, player, v2)?;
 
     Ok(HandleResponse {
-        messages,
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::ForceEndgame { status: Success, game_state: Some(game_state_response), })?),
+        messages: vec![],
+        log,
+        data: Some(to_binary(&HandleAnswer::ForceEndgame { status: Success, game_state, game_state_v2 })?),
     })
 }
 
@@ -1615,7 +2134,7 @@ fn powerup_insurance(
     player: CanonicalAddr,
     token_id: String,
     game_state: &mut GameState,
-) -> StdResult<()> {
+) -> Result<(), ContractError> {
     let mut valid_round = false;
 
     if game_state.round == 0 {
@@ -1634,7 +2153,7 @@ fn powerup_insurance(
     }
 
     if !valid_round {
-        return Err(StdError::generic_err("Invalid round for Insurance powerup"));
+        return Err(ContractError::InvalidPowerupRound {});
     }
 
     if player == game_state.player_a {
@@ -1655,9 +2174,9 @@ pub fn try_receive_nft<S: Storage, A: Api, Q: Querier>(
     from: HumanAddr,
     token_ids: Vec<String>,
     _msg: Option<String>,
-) -> StdResult<HandleResponse> {
+) -> Result<HandleResponse, ContractError> {
     if token_ids.len() != 1 {
-        return Err(StdError::generic_err("Can only send one powerup nft at a time"));
+        return Err(ContractError::MultiplePowerups {});
     }
 
     let player = deps.api.canonical_address(&from)?;
@@ -1665,19 +2184,20 @@ pub fn try_receive_nft<S: Storage, A: Api, Q: Querier>(
     // check if already in an ongoing game
     let current_game = get_current_game(&deps.storage, &player);
     if current_game.is_none() {
-        return Err(StdError::generic_err("You cannot send a powerup nft before joining a game"));
+        return Err(ContractError::NotInGame { action: "send a powerup nft".to_string() });
     }
     let current_game = current_game.unwrap();
         
     let mut game_state: GameState = get_game_state(&deps.storage, current_game)?;
 
     if game_state.finished {
-        return Err(StdError::generic_err("Game is finished, join a new game"));
+        return Err(ContractError::GameFinished {});
     }
+    let from_stage = game_stage(&game_state)?;
 
     if (player == game_state.player_a && game_state.player_a_powerup.is_some()) ||
        (game_state.player_b.is_some() && player == game_state.player_b.clone().unwrap() && game_state.player_b_powerup.is_some()) {
-        return Err(StdError::generic_err("Can only apply one powerup nft per game"));
+        return Err(ContractError::PowerupApplied {});
     }
 
     let config = get_config(&deps.storage)?;
@@ -1702,16 +2222,17 @@ pub fn try_receive_nft<S: Storage, A: Api, Q: Querier>(
             let powerup = extension.description.unwrap();
             match powerup.as_str() {
                 "insurance" => powerup_insurance(player, token_ids[0].clone(), &mut game_state)?,
-                _ => { return Err(StdError::generic_err("You did not send a powerup nft")); }
+                _ => { return Err(ContractError::NotPowerup {}); }
             }
         }
     } else {
-        return Err(StdError::generic_err("Invalid private metadata for powerup nft"));
+        return Err(ContractError::InvalidPowerupMetadata {});
     }
+    update_game_state(&mut deps.storage, current_game, &game_state)?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: game_log(&deps.storage, "batch_receive_nft", current_game, Some(from_stage))?,
         data: Some(to_binary(&HandleAnswer::BatchReceiveNft { status: Success, game_state: None })?),
     })
 }
@@ -1720,7 +2241,7 @@ fn revoke_permit<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     permit_name: String,
-) -> StdResult<HandleResponse> {
+) -> Result<HandleResponse, ContractError> {
     RevokedPermits::revoke_permit(
         &mut deps.storage,
         PREFIX_REVOKED_PERMITS,
@@ -1730,90 +2251,786 @@ fn revoke_permit<S: Storage, A: Api, Q: Querier>(
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: vec![log("action", "revoke_permit")],
         data: Some(to_binary(&HandleAnswer::RevokePermit { status: Success })?),
     })
 }
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::PoolSize { } => query_pool_size(deps),
-        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
+// log attributes of an action moving funds, amounts are in denom
+fn funds_log(action: &str, denom: &str, amount: u128) -> Vec<LogAttribute> {
+    vec![
+        log("action", action),
+        log("denom", denom),
+        log("amount", amount),
+    ]
+}
+
+// sends amount from the sender's balance, all of it if None
+pub fn try_claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<Uint128>,
+    denom: Option<String>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&env.message.sender)?;
+    let config = get_config(&deps.storage)?;
+    let denom = denom.unwrap_or_else(|| config.default_denom().to_string());
+
+    let balance = get_balance(&deps.storage, &player, &denom);
+    let amount = match amount {
+        Some(amount) => amount.u128(),
+        None => balance,
+    };
+    if amount == 0 {
+        return Err(ContractError::NothingToClaim {});
     }
+    if amount > balance {
+        return Err(ContractError::ClaimExceedsBalance { balance: Uint128(balance), denom });
+    }
+    set_balance(&mut deps.storage, &player, &denom, balance - amount)?;
+
+    let msg = payout_msg(&deps.api, &config, env.contract.address, env.message.sender, &denom, amount)?;
+
+    Ok(HandleResponse {
+        messages: vec![msg],
+        log: funds_log("claim", &denom, amount),
+        data: Some(to_binary(&HandleAnswer::Claim { status: Success, amount: Uint128(amount) })?),
+    })
 }
 
-fn query_pool_size<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<Binary> {
-    let pool_size = get_pool(&deps.storage)?;
-    let response = QueryAnswer::PoolSize {
-        amount: Uint128(pool_size),
-        denom: DENOM.to_string(),
+pub fn try_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    player: HumanAddr,
+    received: Option<(String, u128)>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&player)?;
+
+    let (denom, amount) = match sent_funds(&env, &get_config(&deps.storage)?, received)? {
+        Some((denom, amount)) if amount > 0 => (denom, amount),
+        _ => return Err(ContractError::NothingToDeposit {}),
     };
-    to_binary(&response)
+    credit_balance(&mut deps.storage, &player, &denom, amount)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: funds_log("deposit", &denom, amount),
+        data: Some(to_binary(&HandleAnswer::Deposit { status: Success, balance: Uint128(get_balance(&deps.storage, &player, &denom)) })?),
+    })
 }
 
-fn permit_queries<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    permit: Permit,
-    query: QueryWithPermit,
-) -> Result<Binary, StdError> {
-    // Validate permit content
-    let token_address = deps.api.human_address(
-        &get_config(&deps.storage)?.contract_address
-    )?;
+pub fn try_fund_pool<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    received: Option<(String, u128)>,
+) -> Result<HandleResponse, ContractError> {
+    let (denom, amount) = match sent_funds(&env, &get_config(&deps.storage)?, received)? {
+        Some((denom, amount)) if amount > 0 => (denom, amount),
+        _ => return Err(ContractError::NoPoolFunds {}),
+    };
+    add_to_pool(&mut deps.storage, &denom, amount, PoolChangeReason::Funding, None, env.block.height)?;
 
-    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, token_address)?;
+    Ok(HandleResponse {
+        messages: vec![],
+        log: funds_log("fund_pool", &denom, amount),
+        data: Some(to_binary(&HandleAnswer::FundPool { status: Success, pool: Uint128(get_pool(&deps.storage, &denom)?) })?),
+    })
+}
 
-    // Permit validated! We can now execute the query.
-    match query {
-        QueryWithPermit::GameState {} => {
-            if !permit.check_permission(&Permission::Owner) {
-                return Err(StdError::generic_err(format!(
-                    "No permission to query game state, got permissions {:?}",
-                    permit.params.permissions
-                )));
-            }
+// snip-20 tokens sent to the contract, the player is the owner of the tokens
+pub fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rng: &mut Prng,
+    _sender: HumanAddr,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> Result<HandleResponse, ContractError> {
+    let config = get_config(&deps.storage)?;
+    let token = match &config.token {
+        Some(token) => token,
+        None => return Err(ContractError::NoToken {}),
+    };
+    if deps.api.canonical_address(&env.message.sender)? != token.address {
+        return Err(ContractError::UnknownToken {});
+    }
+    let msg: ReceiveMsg = match msg {
+        Some(msg) => from_binary(&msg)?,
+        None => return Err(ContractError::MissingReceiveMsg {}),
+    };
 
-            query_game_state(deps, &account)
-        }
-        QueryWithPermit::PlayerStats {} => {
-            if !permit.check_permission(&Permission::Owner) {
-                return Err(StdError::generic_err(format!(
-                    "No permission to query player stats, got permissions {:?}",
-                    permit.params.permissions
-                )));
-            }
+    // the token's address is the default denom
+    let received = Some((config.default_denom().to_string(), amount.u128()));
+    match msg {
+        ReceiveMsg::Join { stakes, entropy, entropy_commitment, referrer, v2 } => 
+            try_join(deps, env, rng, from, received, stakes, entropy, entropy_commitment, None, None, referrer, v2),
+        ReceiveMsg::CreatePrivateGame { stakes, opponent, invite_code, entropy, entropy_commitment, referrer, v2 } => 
+            try_create_private_game(deps, env, from, received, stakes, opponent, invite_code, entropy, entropy_commitment, None, None, referrer, v2),
+        ReceiveMsg::JoinPrivateGame { host, invite_code, entropy, entropy_commitment, referrer, v2 } => 
+            try_join_private_game(deps, env, rng, from, received, host, invite_code, entropy, entropy_commitment, None, None, referrer, v2),
+        ReceiveMsg::Deposit {} => try_deposit(deps, env, from, received),
+        ReceiveMsg::FundPool {} => try_fund_pool(deps, env, received),
+    }
+}
 
-            query_player_stats(deps, &account)
+pub fn try_set_leaderboard_opt_in<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    opt_in: bool,
+    display_name: Option<String>,
+) -> Result<HandleResponse, ContractError> {
+    let player = deps.api.canonical_address(&env.message.sender)?;
+
+    if let Some(display_name) = &display_name {
+        if display_name.trim().is_empty() || display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+            return Err(ContractError::InvalidDisplayName { max: MAX_DISPLAY_NAME_LENGTH as u32 });
         }
     }
+    set_leaderboard_opt_in(&mut deps.storage, &player, opt_in, display_name)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_leaderboard_opt_in")],
+        data: Some(to_binary(&HandleAnswer::SetLeaderboardOptIn { status: Success })?),
+    })
 }
 
-fn color_to_string(color: Color) -> String {
-    match color {
-        Color::Red => "color:red".to_string(),
-        Color::Green => "color:green".to_string(),
-        Color::Blue => "color:blue".to_string(),
-        Color::Black => "color:black".to_string(),
+fn check_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+) -> Result<(), ContractError> {
+    if deps.api.canonical_address(&env.message.sender)? != config.admin {
+        return Err(ContractError::AdminOnly {});
     }
+    Ok(())
 }
 
-fn shape_to_string(shape: Shape) -> String {
-    match shape {
-        Shape::Triangle => "shape:triangle".to_string(),
-        Shape::Square => "shape:square".to_string(),
-        Shape::Circle => "shape:circle".to_string(),
-        Shape::Star => "shape:star".to_string(),
+// pools, escrow, balances, pending withdrawals and lobbies are only reported for the accepted
+// denoms, so a denom can only be removed once nothing is held or waiting in it
+fn check_denom_removable<S: Storage>(
+    storage: &S,
+    denom: &str,
+) -> Result<(), ContractError> {
+    if get_pending_withdrawal(storage, denom).is_some() {
+        return Err(ContractError::WithdrawalPending { denom: denom.to_string() });
     }
+    if get_pool(storage, denom)? > 0 || get_escrow(storage, denom) > 0 || get_balance_total(storage, denom) > 0 {
+        return Err(ContractError::DenomInUse { denom: denom.to_string() });
+    }
+    for stakes in &[StakesTier::Practice, StakesTier::Low, StakesTier::Medium, StakesTier::High] {
+        for entry in get_lobby(storage, stakes.u8_val(), denom) {
+            let game_state = get_game_state(storage, entry.game_idx)?;
+            if !game_state.finished && game_state.player_b.is_none() {
+                return Err(ContractError::DenomInUse { denom: denom.to_string() });
+            }
+        }
+    }
+    Ok(())
 }
 
-fn hint_to_string(hint: Hint) -> String {
-    match hint {
-        Hint::NobodyHasRed => "nobody_has|color:red".to_string(),
+fn try_update_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    update: ConfigUpdate,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    // games already created keep the rules they were created with
+    config.red_weight = update.red_weight.unwrap_or(config.red_weight);
+    config.green_weight = update.green_weight.unwrap_or(config.green_weight);
+    config.blue_weight = update.blue_weight.unwrap_or(config.blue_weight);
+    config.black_weight = update.black_weight.unwrap_or(config.black_weight);
+    config.triangle_weight = update.triangle_weight.unwrap_or(config.triangle_weight);
+    config.square_weight = update.square_weight.unwrap_or(config.square_weight);
+    config.circle_weight = update.circle_weight.unwrap_or(config.circle_weight);
+    config.star_weight = update.star_weight.unwrap_or(config.star_weight);
+    if let Some(low_stakes) = update.low_stakes {
+        config.denoms[0].low_stakes = low_stakes.u128();
+    }
+    if let Some(medium_stakes) = update.medium_stakes {
+        config.denoms[0].medium_stakes = medium_stakes.u128();
+    }
+    if let Some(high_stakes) = update.high_stakes {
+        config.denoms[0].high_stakes = high_stakes.u128();
+    }
+    if let Some(denoms) = update.denoms {
+        let denoms = denom_configs(denoms);
+        for old in &config.denoms[1..] {
+            if !denoms.iter().any(|new| new.denom == old.denom) {
+                check_denom_removable(&deps.storage, &old.denom)?;
+            }
+        }
+        config.denoms.truncate(1);
+        config.denoms.extend(denoms);
+    }
+    config.timeout = update.timeout.unwrap_or(config.timeout);
+    config.rounds_per_game = update.rounds_per_game.unwrap_or(config.rounds_per_game);
+    if update.lobby_policy.is_some() {
+        config.lobby_policy = parse_lobby_policy(update.lobby_policy)?.u8_val();
+    }
+    config.lobby_expiration = update.lobby_expiration.unwrap_or(config.lobby_expiration);
+    validate_config(&config)?;
+
+    set_config(&mut deps.storage, config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "update_config")],
+        data: Some(to_binary(&HandleAnswer::UpdateConfig { status: Success })?),
+    })
+}
+
+fn try_change_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    config.admin = deps.api.canonical_address(&address)?;
+    set_config(&mut deps.storage, config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "change_admin")],
+        data: Some(to_binary(&HandleAnswer::ChangeAdmin { status: Success })?),
+    })
+}
+
+fn try_set_minter<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    minter: ContractInfo,
+) -> Result<HandleResponse, ContractError> {
+    let config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    let stored_minter = StoreContractInfo {
+        address: deps.api.canonical_address(&minter.address)?,
+        code_hash: minter.code_hash.clone(),
+    };
+    set_minter(&mut deps.storage, stored_minter)?;
+
+    // register with the new minter the same way as in init
+    Ok(HandleResponse {
+        messages: vec![
+            register_receive_nft_msg(
+                env.contract_code_hash,
+                Some(true),
+                None,
+                256,
+                minter.code_hash.clone(),
+                minter.address.clone(),
+            )?,
+            set_viewing_key_msg(
+                config.viewing_key,
+                None,
+                256,
+                minter.code_hash,
+                minter.address,
+            )?,
+        ],
+        log: vec![log("action", "set_minter")],
+        data: Some(to_binary(&HandleAnswer::SetMinter { status: Success })?),
+    })
+}
+
+fn try_set_rake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rake_bps: u16,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    // games already created keep the rake they were created with
+    config.rake_bps = rake_bps;
+    validate_config(&config)?;
+    set_config(&mut deps.storage, config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_rake")],
+        data: Some(to_binary(&HandleAnswer::SetRake { status: Success })?),
+    })
+}
+
+fn try_set_fee_routing<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pool_bps: u16,
+    treasury_bps: u16,
+    referral_bps: u16,
+    treasury: Option<HumanAddr>,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    config.rake_pool_bps = pool_bps;
+    config.rake_treasury_bps = treasury_bps;
+    config.rake_referral_bps = referral_bps;
+    config.treasury = match treasury {
+        Some(treasury) => Some(deps.api.canonical_address(&treasury)?),
+        None => None,
+    };
+    validate_config(&config)?;
+    set_config(&mut deps.storage, config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_fee_routing")],
+        data: Some(to_binary(&HandleAnswer::SetFeeRouting { status: Success })?),
+    })
+}
+
+fn try_set_jackpot_rule<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rule: JackpotRule,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    config.jackpot_rule = rule.to_stored()?;
+    set_config(&mut deps.storage, config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_jackpot_rule")],
+        data: Some(to_binary(&HandleAnswer::SetJackpotRule { status: Success })?),
+    })
+}
+
+fn try_propose_pool_withdrawal<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Uint128,
+    denom: Option<String>,
+    recipient: Option<HumanAddr>,
+) -> Result<HandleResponse, ContractError> {
+    let config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    let denom = denom.unwrap_or_else(|| config.default_denom().to_string());
+    if config.denom_config(&denom).is_none() {
+        return Err(ContractError::DenomNotAccepted { denom });
+    }
+    if amount.u128() == 0 {
+        return Err(ContractError::ZeroWithdrawal {});
+    }
+    let recipient = match recipient {
+        Some(recipient) => deps.api.canonical_address(&recipient)?,
+        None => config.admin.clone(),
+    };
+
+    // the pool is only checked when the withdrawal is executed, as it can change in between
+    let executable_block = env.block.height.checked_add(config.pool_withdrawal_delay)
+        .ok_or(ContractError::InvalidPoolWithdrawalDelay { min: MIN_POOL_WITHDRAWAL_DELAY, max: MAX_POOL_WITHDRAWAL_DELAY })?;
+    set_pending_withdrawal(&mut deps.storage, &denom, &PendingWithdrawal {
+        amount: amount.u128(),
+        recipient,
+        proposed_block: env.block.height,
+        executable_block,
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: funds_log("propose_pool_withdrawal", &denom, amount.u128()),
+        data: Some(to_binary(&HandleAnswer::ProposePoolWithdrawal { status: Success, executable_block })?),
+    })
+}
+
+fn try_execute_pool_withdrawal<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    denom: Option<String>,
+) -> Result<HandleResponse, ContractError> {
+    let config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    let denom = denom.unwrap_or_else(|| config.default_denom().to_string());
+    let withdrawal = match get_pending_withdrawal(&deps.storage, &denom) {
+        Some(withdrawal) => withdrawal,
+        None => return Err(ContractError::NoPendingWithdrawal { denom }),
+    };
+    if env.block.height < withdrawal.executable_block {
+        return Err(ContractError::WithdrawalLocked { executable_block: withdrawal.executable_block });
+    }
+
+    take_from_pool(&mut deps.storage, &denom, withdrawal.amount, PoolChangeReason::Withdrawal, None, env.block.height)?;
+    remove_pending_withdrawal(&mut deps.storage, &denom);
+
+    let recipient = deps.api.human_address(&withdrawal.recipient)?;
+    let msg = payout_msg(&deps.api, &config, env.contract.address, recipient, &denom, withdrawal.amount)?;
+
+    Ok(HandleResponse {
+        messages: vec![msg],
+        log: funds_log("execute_pool_withdrawal", &denom, withdrawal.amount),
+        data: Some(to_binary(&HandleAnswer::ExecutePoolWithdrawal { status: Success, pool: Uint128(get_pool(&deps.storage, &denom)?) })?),
+    })
+}
+
+fn try_set_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    level: ContractStatus,
+) -> Result<HandleResponse, ContractError> {
+    let mut config = get_config(&deps.storage)?;
+    check_admin(deps, &env, &config)?;
+
+    let new_status = level.to_u8();
+    if config.status != new_status {
+        config.status = new_status;
+        set_config(&mut deps.storage, config)?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_contract_status")],
+        data: Some(to_binary(&HandleAnswer::SetContractStatus { status: Success })?),
+    })
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    let response = match msg {
+        QueryMsg::PoolSize { } => query_pool_size(deps),
+        QueryMsg::Jackpot { denom, game_idx } => query_jackpot(deps, denom, game_idx),
+        QueryMsg::ContractStatus { } => query_contract_status(deps),
+        QueryMsg::Leaderboard { page, page_size, sort_by } => query_leaderboard(deps, page, page_size, sort_by),
+        QueryMsg::VerifyDeal { game_idx } => query_verify_deal(deps, game_idx),
+        QueryMsg::PendingWithdrawals { } => query_pending_withdrawals(deps),
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
+    };
+
+    response.map_err(StdError::from)
+}
+
+fn query_jackpot<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    denom: Option<String>,
+    game_idx: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let config = get_config(&deps.storage)?;
+    // a game pays the rake it was created with
+    let (denom, rake_bps) = match game_idx {
+        Some(game_idx) => {
+            let game_state = get_game_state(&deps.storage, game_idx)?;
+            (game_state.denom, game_state.rake_bps)
+        },
+        None => (denom.unwrap_or_else(|| config.default_denom().to_string()), config.rake_bps),
+    };
+    if config.denom_config(&denom).is_none() {
+        return Err(ContractError::DenomNotAccepted { denom });
+    }
+    let pool = get_pool(&deps.storage, &denom)?;
+    let jackpot = config.jackpot_rule.jackpot(pool);
+    let response = QueryAnswer::Jackpot {
+        denom,
+        pool: Uint128(pool),
+        rule: config.jackpot_rule.to_humanized(),
+        amount: Uint128(jackpot),
+        after_rake: Uint128(jackpot - jackpot * rake_bps as u128 / 10000),
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn query_pool_size<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> Result<Binary, ContractError> {
+    let config = get_config(&deps.storage)?;
+    let mut pools: Vec<Coin> = vec![];
+    for denom in &config.denoms {
+        pools.push(Coin {
+            denom: denom.denom.clone(),
+            amount: Uint128(get_pool(&deps.storage, &denom.denom)?),
+        });
+    }
+    let response = QueryAnswer::PoolSize {
+        amount: pools[0].amount,
+        denom: pools[0].denom.clone(),
+        pools,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn query_pending_withdrawals<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> Result<Binary, ContractError> {
+    let config = get_config(&deps.storage)?;
+    let mut withdrawals: Vec<PendingWithdrawalResponse> = vec![];
+    for denom in &config.denoms {
+        if let Some(withdrawal) = get_pending_withdrawal(&deps.storage, &denom.denom) {
+            withdrawals.push(PendingWithdrawalResponse {
+                denom: denom.denom.clone(),
+                amount: Uint128(withdrawal.amount),
+                recipient: deps.api.human_address(&withdrawal.recipient)?,
+                proposed_block: withdrawal.proposed_block,
+                executable_block: withdrawal.executable_block,
+            });
+        }
+    }
+    let response = QueryAnswer::PendingWithdrawals {
+        delay: config.pool_withdrawal_delay,
+        withdrawals,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn query_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> Result<Binary, ContractError> {
+    let config = get_config(&deps.storage)?;
+    let response = QueryAnswer::ContractStatus {
+        status: ContractStatus::from_u8(config.status)?,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn query_verify_deal<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    game_idx: u32,
+) -> Result<Binary, ContractError> {
+    let game_state = get_game_state(&deps.storage, game_idx)?;
+    let weights = &game_state.chip_weights;
+
+    let mut rounds: Vec<DealResponse> = vec![];
+    for (i, round_state) in game_state.rounds().into_iter().enumerate() {
+        let mut deal = DealResponse {
+            round: (i + 1) as u8,
+            deal_commitment: hex::encode(&round_state.deal_commitment),
+            contract_seed: None,
+            deal_seed: None,
+            bag_chip_color: None,
+            bag_chip_shape: None,
+            player_a_chip_color: None,
+            player_a_chip_shape: None,
+            player_b_chip_color: None,
+            player_b_chip_shape: None,
+            player_a_first_hint: None,
+            player_b_first_hint: None,
+        };
+        // nothing about the deal is revealed while the game is still being played
+        if game_state.finished {
+            deal.contract_seed = Some(hex::encode(&round_state.contract_seed));
+            deal.deal_seed = Some(hex::encode(&round_state.deal_seed));
+            deal.bag_chip_color = Some(color_to_string(Color::from_u8(round_state.bag_chip.color)?));
+            deal.bag_chip_shape = Some(shape_to_string(Shape::from_u8(round_state.bag_chip.shape)?));
+            deal.player_a_chip_color = Some(color_to_string(Color::from_u8(round_state.player_a_chip.color)?));
+            deal.player_a_chip_shape = Some(shape_to_string(Shape::from_u8(round_state.player_a_chip.shape)?));
+            deal.player_b_chip_color = Some(color_to_string(Color::from_u8(round_state.player_b_chip.color)?));
+            deal.player_b_chip_shape = Some(shape_to_string(Shape::from_u8(round_state.player_b_chip.shape)?));
+            deal.player_a_first_hint = Some(hint_to_string(Hint::from_u8(round_state.player_a_first_hint)?));
+            deal.player_b_first_hint = Some(hint_to_string(Hint::from_u8(round_state.player_b_first_hint)?));
+        }
+        rounds.push(deal);
+    }
+
+    let (player_a_entropy_hash, player_b_entropy_hash) = if game_state.finished {
+        (game_state.player_a_entropy.as_ref().map(hex::encode), game_state.player_b_entropy.as_ref().map(hex::encode))
+    } else {
+        (None, None)
+    };
+
+    let response = QueryAnswer::VerifyDeal {
+        finished: game_state.finished,
+        player_a_entropy_hash,
+        player_b_entropy_hash,
+        color_weights: vec![weights.red_weight, weights.green_weight, weights.blue_weight, weights.black_weight],
+        shape_weights: vec![weights.triangle_weight, weights.square_weight, weights.circle_weight, weights.star_weight],
+        rounds,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn win_rate(stats: &PlayerStats) -> u32 {
+    if stats.games_played == 0 {
+        return 0;
+    }
+    (stats.wins as u64 * 10000 / stats.games_played as u64) as u32
+}
+
+fn query_leaderboard<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    sort_by: Option<String>,
+) -> Result<Binary, ContractError> {
+    let (skip, page_size) = page_bounds(page, page_size);
+
+    // only players who opted in are on the leaderboard, nobody else's stats are read
+    let mut ranked: Vec<(LeaderboardEntry, PlayerStats)> = get_leaderboard(&deps.storage)?
+        .into_iter()
+        .map(|entry| {
+            let stats = get_player_stats(&deps.storage, &entry.player);
+            (entry, stats)
+        })
+        .collect();
+    match sort_by.as_deref() {
+        None | Some("wins") => ranked.sort_by(|a, b| b.1.wins.cmp(&a.1.wins)),
+        Some("win_rate") => ranked.sort_by(|a, b| win_rate(&b.1).cmp(&win_rate(&a.1))),
+        Some("net_winnings") => ranked.sort_by(|a, b| b.1.net_winnings().cmp(&a.1.net_winnings())),
+        Some(_) => return Err(ContractError::InvalidSort {}),
+    }
+
+    let total = ranked.len() as u32;
+    let mut players: Vec<LeaderboardPlayerResponse> = vec![];
+    for (i, (entry, stats)) in ranked.into_iter().enumerate().skip(skip as usize).take(page_size as usize) {
+        players.push(LeaderboardPlayerResponse {
+            rank: i as u32 + 1,
+            player: deps.api.human_address(&entry.player)?,
+            display_name: entry.display_name,
+            games_played: stats.games_played,
+            wins: stats.wins,
+            win_rate: win_rate(&stats),
+            net_winnings: stats.net_winnings().to_string(),
+        });
+    }
+
+    let response = QueryAnswer::Leaderboard {
+        total,
+        players,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn permit_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> Result<Binary, ContractError> {
+    // Validate permit content
+    let token_address = deps.api.human_address(
+        &get_config(&deps.storage)?.contract_address
+    )?;
+
+    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, token_address)?;
+
+    // Permit validated! We can now execute the query.
+    match query {
+        QueryWithPermit::GameState {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "game state".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_game_state(deps, &account)
+        }
+        QueryWithPermit::GameStateV2 {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "game state".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_game_state_v2(deps, &account)
+        }
+        QueryWithPermit::PlayerStats {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "player stats".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_player_stats(deps, &account)
+        }
+        QueryWithPermit::Balance {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "balance".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_balance(deps, &account)
+        }
+        QueryWithPermit::GameHistory { page, page_size } => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "game history".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_game_history(deps, &account, page, page_size)
+        }
+        QueryWithPermit::GameTranscript { game_idx } => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "game transcript".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+
+            query_game_transcript(deps, &account, game_idx)
+        }
+        QueryWithPermit::PoolLog { page, page_size } => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "pool log".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+            check_admin_account(deps, &account)?;
+
+            query_pool_log(deps, page, page_size)
+        }
+        QueryWithPermit::Solvency {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoPermission {
+                    query: "solvency".to_string(),
+                    permissions: format!("{:?}", permit.params.permissions),
+                });
+            }
+            check_admin_account(deps, &account)?;
+
+            query_solvency(deps)
+        }
+    }
+}
+
+// like check_admin, for the account that signed a permit query
+fn check_admin_account<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+) -> Result<(), ContractError> {
+    if deps.api.canonical_address(account)? != get_config(&deps.storage)?.admin {
+        return Err(ContractError::AdminQueryOnly {});
+    }
+    Ok(())
+}
+
+fn color_to_string(color: Color) -> String {
+    match color {
+        Color::Red => "color:red".to_string(),
+        Color::Green => "color:green".to_string(),
+        Color::Blue => "color:blue".to_string(),
+        Color::Black => "color:black".to_string(),
+    }
+}
+
+fn shape_to_string(shape: Shape) -> String {
+    match shape {
+        Shape::Triangle => "shape:triangle".to_string(),
+        Shape::Square => "shape:square".to_string(),
+        Shape::Circle => "shape:circle".to_string(),
+        Shape::Star => "shape:star".to_string(),
+    }
+}
+
+fn hint_to_string(hint: Hint) -> String {
+    match hint {
+        Hint::NobodyHasRed => "nobody_has|color:red".to_string(),
         Hint::NobodyHasGreen => "nobody_has|color:green".to_string(),
         Hint::NobodyHasBlue => "nobody_has|color:blue".to_string(),
         Hint::NobodyHasBlack => "nobody_has|color:black".to_string(),
@@ -1864,14 +3081,42 @@ fn round_result_to_string(round_result: RoundResult) -> String {
     }
 }
 
-fn pick_to_string(pick: u8) -> String {
-    match pick {
+fn game_result_to_string(game_result: GameResult, is_player_a: bool) -> String {
+    let (won_wager, won_jackpot, won_nft) = if is_player_a {
+        (GameResult::AWon, GameResult::AJackpotBNft, GameResult::ANftBJackpot)
+    } else {
+        (GameResult::BWon, GameResult::ANftBJackpot, GameResult::AJackpotBNft)
+    };
+    if game_result == won_wager {
+        "you won wager".to_string()
+    } else if game_result == won_jackpot {
+        "you won jackpot".to_string()
+    } else if game_result == won_nft {
+        "you won nft".to_string()
+    } else if game_result == GameResult::NoReward {
+        "you lost reward".to_string()
+    } else {
+        "you lost wager".to_string()
+    }
+}
+
+fn pick_to_string(pick: u8) -> String {
+    match pick {
         REWARD_POOL => "jackpot".to_string(),
         REWARD_NFT => "nft".to_string(),
         _ => "".to_string(),
     }
 }
 
+fn stakes_to_string(stakes: StakesTier) -> String {
+    match stakes {
+        StakesTier::Practice => "practice".to_string(),
+        StakesTier::Low => "low".to_string(),
+        StakesTier::Medium => "medium".to_string(),
+        StakesTier::High => "high".to_string(),
+    }
+}
+
 fn powerup_to_string(powerup: u16) -> String {
     match powerup {
         POWERUP_INSURANCE => "insurance".to_string(),
@@ -1895,47 +3140,158 @@ fn bitmask_to_string(bitmask: u8) -> String {
 }
 */
 
-fn get_game_state_response<S: Storage>(
+fn get_round_history_response(
+    round_history: &[RoundState],
+    is_player_a: bool,
+) -> Result<Vec<RoundHistoryV2Response>, ContractError> {
+    let mut history: Vec<RoundHistoryV2Response> = vec![];
+    for (i, round_state) in round_history.iter().enumerate() {
+        let round_state = round_state.clone();
+        let (chip, own_guess, other_guess, own_result, other_result) = if is_player_a {
+            (round_state.player_a_chip, round_state.player_a_guess, round_state.player_b_guess,
+             round_state.player_a_round_result, round_state.player_b_round_result)
+        } else {
+            (round_state.player_b_chip, round_state.player_b_guess, round_state.player_a_guess,
+             round_state.player_b_round_result, round_state.player_a_round_result)
+        };
+        let guess = match own_guess {
+            Some(own_guess) => Some(own_guess.to_humanized()?),
+            None => None,
+        };
+        let opponent_guess = match other_guess {
+            Some(other_guess) => Some(other_guess.to_humanized()?),
+            None => None,
+        };
+        let round_result = match own_result {
+            Some(own_result) => Some(RoundResult::from_u8(own_result)?),
+            None => None,
+        };
+        let opponent_round_result = match other_result {
+            Some(other_result) => Some(RoundResult::from_u8(other_result)?),
+            None => None,
+        };
+        history.push(RoundHistoryV2Response {
+            round: (i + 1) as u8,
+            chip: chip.to_humanized()?,
+            guess,
+            opponent_guess,
+            round_result,
+            opponent_round_result,
+        });
+    }
+    Ok(history)
+}
+
+// whether a submission contradicts the other player's chip or first hint, None if not submitted
+fn submit_provably_false(
+    submit: Option<u8>,
+    other_player_chip: &Chip,
+    other_player_first_hint: u8,
+) -> Result<Option<bool>, ContractError> {
+    match submit {
+        Some(submit) => Ok(Some(submission_provably_false(
+            Hint::from_u8(submit)?,
+            other_player_chip.to_bitmask(),
+            Hint::from_u8(other_player_first_hint)?.to_bitmask(),
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn get_round_reveal_response(
+    rounds: &[RoundState],
+    is_player_a: bool,
+) -> Result<Vec<RoundRevealV2Response>, ContractError> {
+    let mut reveal: Vec<RoundRevealV2Response> = vec![];
+    for (i, round_state) in rounds.iter().enumerate() {
+        let chip_a = round_state.player_a_chip.to_humanized()?;
+        let chip_b = round_state.player_b_chip.to_humanized()?;
+        let a_first = submit_provably_false(round_state.player_a_first_submit, &chip_b, round_state.player_b_first_hint)?;
+        let a_second = submit_provably_false(round_state.player_a_second_submit, &chip_b, round_state.player_b_first_hint)?;
+        let b_first = submit_provably_false(round_state.player_b_first_submit, &chip_a, round_state.player_a_first_hint)?;
+        let b_second = submit_provably_false(round_state.player_b_second_submit, &chip_a, round_state.player_a_first_hint)?;
+        let (opponent_chip, opponent_hint, own_false, opponent_false) = if is_player_a {
+            (chip_b, round_state.player_b_first_hint, (a_first, a_second), (b_first, b_second))
+        } else {
+            (chip_a, round_state.player_a_first_hint, (b_first, b_second), (a_first, a_second))
+        };
+        reveal.push(RoundRevealV2Response {
+            round: (i + 1) as u8,
+            bag_chip: round_state.bag_chip.to_humanized()?,
+            opponent_chip,
+            opponent_hint: Hint::from_u8(opponent_hint)?,
+            first_submit_provably_false: own_false.0,
+            second_submit_provably_false: own_false.1,
+            opponent_first_submit_provably_false: opponent_false.0,
+            opponent_second_submit_provably_false: opponent_false.1,
+        });
+    }
+    Ok(reveal)
+}
+
+fn get_game_state_v2_response<S: Storage>(
     storage: &S,
     player: CanonicalAddr,
-) -> StdResult<GameStateResponse> {
+) -> Result<GameStateV2Response, ContractError> {
     let mut round: Option<u8> = None;
+    let mut rounds_per_game: Option<u8> = None;
+    let mut stakes: Option<String> = None;
+    let mut private_game: Option<bool> = None;
+    let mut round_wins: Option<u8> = None;
+    let mut opponent_round_wins: Option<u8> = None;
+    let mut round_history: Option<Vec<RoundHistoryV2Response>> = None;
     let mut wager: Option<Uint128> = None;
-    let mut chip_color: Option<String> = None;
-    let mut chip_shape: Option<String> = None;
-    let mut hint: Option<String> = None;
+    let mut chip: Option<Chip> = None;
+    let mut hint: Option<Hint> = None;
     let mut powerup: Option<String> = None;
     let mut first_round_start_block: Option<u64> = None;
-    let mut first_submit: Option<String> = None;
+    let mut deal_commitment: Option<String> = None;
+    let mut first_submit: Option<Hint> = None;
     let mut first_submit_block: Option<u64> = None;
-    let mut opponent_first_submit: Option<String> = None;
-    let mut first_extra_secret: Option<String> = None;
+    let mut opponent_first_submit: Option<Hint> = None;
+    let mut first_extra_secret: Option<Hint> = None;
     let mut second_submit_turn_start_block: Option<u64> = None;
-    let mut second_submit: Option<String> = None;
+    let mut second_submit: Option<Hint> = None;
     let mut second_submit_block: Option<u64> = None;
-    let mut opponent_second_submit: Option<String> = None;
-    let mut second_extra_secret: Option<String> = None;
+    let mut opponent_second_submit: Option<Hint> = None;
+    let mut second_extra_secret: Option<Hint> = None;
     let mut guess_turn_start_block: Option<u64> = None;
-    let mut guess: Option<String> = None;
+    let mut guess: Option<Guess> = None;
     let mut guess_block: Option<u64> = None;
-    let mut opponent_guess: Option<String> = None;
-    let mut round_result: Option<String> = None;
-    let mut opponent_round_result: Option<String> = None;
+    let mut opponent_guess: Option<Guess> = None;
+    let mut round_result: Option<RoundResult> = None;
+    let mut opponent_round_result: Option<RoundResult> = None;
     let mut pick_reward_round_start_block: Option<u64> = None;
     let mut finished: Option<bool> = None;
-    let mut result: Option<String> = None;
+    let mut is_player_a: Option<bool> = None;
+    let mut result: Option<GameResult> = None;
     let mut opponent_powerup: Option<String> = None;
     let mut pick: Option<String> = None;
     let mut jackpot_reward: Option<Uint128> = None;
     let mut nft_token_id: Option<String> = None;
+    let mut reveal: Option<Vec<RoundRevealV2Response>> = None;
 
     let current_game = get_current_game(storage, &player);
     if current_game.is_some() {
         let game_state: GameState = get_game_state(storage, current_game.unwrap())?;
+        let round_outcomes = game_state.round_outcomes()?;
+        let player_a_round_wins = round_outcomes.iter().filter(|o| **o == RoundOutcome::AWon).count() as u8;
+        let player_b_round_wins = round_outcomes.iter().filter(|o| **o == RoundOutcome::BWon).count() as u8;
+        let in_reward_round = game_state.in_reward_round();
         if player == game_state.player_a {
             wager = Some(Uint128(game_state.player_a_wager.unwrap_or(0)));
             round = Some(game_state.round);
+            rounds_per_game = Some(game_state.rounds_per_game);
+            stakes = Some(stakes_to_string(StakesTier::from_u8(game_state.stakes)?));
+            private_game = Some(game_state.is_private());
+            round_wins = Some(player_a_round_wins);
+            opponent_round_wins = Some(player_b_round_wins);
+            round_history = Some(get_round_history_response(&game_state.round_history, true)?);
             finished = Some(game_state.finished);
+            is_player_a = Some(true);
+            if game_state.finished {
+                reveal = Some(get_round_reveal_response(&game_state.rounds(), true)?);
+            }
             if game_state.player_a_powerup.is_some() {
                 powerup = Some(powerup_to_string(game_state.player_a_powerup.unwrap()));
             }
@@ -1944,19 +3300,12 @@ fn get_game_state_response<S: Storage>(
             }
             if game_state.result.is_some() {
                 let game_result = GameResult::from_u8(game_state.result.unwrap())?;
-                if game_result == GameResult::AWon {
-                    result = Some("you won wager".to_string());
-                } else if game_result == GameResult::BWon || game_result == GameResult::BothLose {
-                    result = Some("you lost wager".to_string());
-                } else if game_result == GameResult::AJackpotBNft {
-                    result = Some("you won jackpot".to_string());
+                if game_result == GameResult::AJackpotBNft {
                     jackpot_reward = Some(Uint128(game_state.jackpot_reward.unwrap_or(0)));
                 } else if game_result == GameResult::ANftBJackpot {
-                    result = Some("you won nft".to_string());
                     nft_token_id = game_state.nft_token_id;
-                } else if game_result == GameResult::NoReward {
-                    result = Some("you lost reward".to_string());
                 }
+                result = Some(game_result);
 
                 // check if we should share opponent powerup
                 if game_state.player_b_powerup_applied {
@@ -1966,20 +3315,19 @@ fn get_game_state_response<S: Storage>(
             if game_state.round_state.is_some() {
                 let round_state = game_state.round_state.unwrap();
                 first_round_start_block = Some(round_state.round_start_block);
-                let chip = round_state.player_a_chip;
-                chip_color = Some(color_to_string(Color::from_u8(chip.color)?));
-                chip_shape = Some(shape_to_string(Shape::from_u8(chip.shape)?));
+                deal_commitment = Some(hex::encode(&round_state.deal_commitment));
+                chip = Some(round_state.player_a_chip.to_humanized()?);
                 let initial_hint = round_state.player_a_first_hint;
-                hint = Some(hint_to_string(Hint::from_u8(initial_hint)?));
+                hint = Some(Hint::from_u8(initial_hint)?);
                 if round_state.player_a_first_submit.is_some() {
-                    first_submit = Some(hint_to_string(Hint::from_u8(round_state.player_a_first_submit.unwrap())?));
+                    first_submit = Some(Hint::from_u8(round_state.player_a_first_submit.unwrap())?);
                     first_submit_block = round_state.player_a_first_submit_block;
                     // player cannot see opponent's submission until made own submission
                     if round_state.player_b_first_submit.is_some() {
                         if round_state.player_a_first_extra_secret.is_some() {
-                            first_extra_secret = Some(hint_to_string(Hint::from_u8(round_state.player_a_first_extra_secret.unwrap())?));
+                            first_extra_secret = Some(Hint::from_u8(round_state.player_a_first_extra_secret.unwrap())?);
                         } else {
-                            opponent_first_submit = Some(hint_to_string(Hint::from_u8(round_state.player_b_first_submit.unwrap())?));
+                            opponent_first_submit = Some(Hint::from_u8(round_state.player_b_first_submit.unwrap())?);
                         }
                         second_submit_turn_start_block = Some(max(
                             round_state.player_a_first_submit_block.unwrap(),
@@ -1988,14 +3336,14 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_a_second_submit.is_some() {
-                    second_submit = Some(hint_to_string(Hint::from_u8(round_state.player_a_second_submit.unwrap())?));
+                    second_submit = Some(Hint::from_u8(round_state.player_a_second_submit.unwrap())?);
                     second_submit_block = round_state.player_a_second_submit_block;
                     // player cannot see opponent's submission until made own submission
                     if round_state.player_b_second_submit.is_some() {
                         if round_state.player_a_second_extra_secret.is_some() {
-                            second_extra_secret = Some(hint_to_string(Hint::from_u8(round_state.player_a_second_extra_secret.unwrap())?));
+                            second_extra_secret = Some(Hint::from_u8(round_state.player_a_second_extra_secret.unwrap())?);
                         } else {
-                            opponent_second_submit = Some(hint_to_string(Hint::from_u8(round_state.player_b_second_submit.unwrap())?));
+                            opponent_second_submit = Some(Hint::from_u8(round_state.player_b_second_submit.unwrap())?);
                         }
                         guess_turn_start_block = Some(max(
                             round_state.player_a_second_submit_block.unwrap(),
@@ -2004,12 +3352,12 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_a_guess.is_some() {
-                    guess = Some(guess_to_string(round_state.player_a_guess.unwrap().to_humanized()?));
+                    guess = Some(round_state.player_a_guess.unwrap().to_humanized()?);
                     guess_block = round_state.player_a_guess_block;
                     // player cannot see opponent's guess until made own guess
                     if round_state.player_b_guess.is_some() {
-                        opponent_guess = Some(guess_to_string(round_state.player_b_guess.unwrap().to_humanized()?));
-                        if game_state.round == 3 {
+                        opponent_guess = Some(round_state.player_b_guess.unwrap().to_humanized()?);
+                        if in_reward_round {
                             // went to pick reward round, send block when started
                             pick_reward_round_start_block = Some(max(
                                 round_state.player_a_guess_block.unwrap(),
@@ -2019,17 +3367,27 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_a_round_result.is_some() {
-                    round_result = Some(round_result_to_string(RoundResult::from_u8(round_state.player_a_round_result.unwrap())?));
+                    round_result = Some(RoundResult::from_u8(round_state.player_a_round_result.unwrap())?);
                     // player cannot see opponent's round result until own round result if available
                     if round_state.player_b_round_result.is_some() {
-                        opponent_round_result = Some(round_result_to_string(RoundResult::from_u8(round_state.player_b_round_result.unwrap())?));
+                        opponent_round_result = Some(RoundResult::from_u8(round_state.player_b_round_result.unwrap())?);
                     }
                 }
             }
-        } else if player == game_state.player_b.unwrap() {
+        } else if player == game_state.player_b.clone().unwrap() {
             wager = Some(Uint128(game_state.player_b_wager.unwrap_or(0)));
             round = Some(game_state.round);
+            rounds_per_game = Some(game_state.rounds_per_game);
+            stakes = Some(stakes_to_string(StakesTier::from_u8(game_state.stakes)?));
+            private_game = Some(game_state.is_private());
+            round_wins = Some(player_b_round_wins);
+            opponent_round_wins = Some(player_a_round_wins);
+            round_history = Some(get_round_history_response(&game_state.round_history, false)?);
             finished = Some(game_state.finished);
+            is_player_a = Some(false);
+            if game_state.finished {
+                reveal = Some(get_round_reveal_response(&game_state.rounds(), false)?);
+            }
             if game_state.player_b_powerup.is_some() {
                 powerup = Some(powerup_to_string(game_state.player_b_powerup.unwrap()));
             }
@@ -2038,19 +3396,12 @@ fn get_game_state_response<S: Storage>(
             }
             if game_state.result.is_some() {
                 let game_result = GameResult::from_u8(game_state.result.unwrap())?;
-                if game_result == GameResult::BWon {
-                    result = Some("you won wager".to_string());
-                } else if game_result == GameResult::AWon || game_result == GameResult::BothLose {
-                    result = Some("you lost wager".to_string());
-                } else if game_result == GameResult::AJackpotBNft {
-                    result = Some("you won nft".to_string());
+                if game_result == GameResult::AJackpotBNft {
                     nft_token_id = game_state.nft_token_id;
                 } else if game_result == GameResult::ANftBJackpot {
-                    result = Some("you won jackpot".to_string());
                     jackpot_reward = Some(Uint128(game_state.jackpot_reward.unwrap_or(0)));
-                } else if game_result == GameResult::NoReward {
-                    result = Some("you lost reward".to_string());
                 }
+                result = Some(game_result);
 
                 // check if we should share opponent powerup
                 if game_state.player_a_powerup_applied {
@@ -2060,20 +3411,19 @@ fn get_game_state_response<S: Storage>(
             if game_state.round_state.is_some() {
                 let round_state = game_state.round_state.unwrap();
                 first_round_start_block = Some(round_state.round_start_block);
-                let chip = round_state.player_b_chip;
-                chip_color = Some(color_to_string(Color::from_u8(chip.color)?));
-                chip_shape = Some(shape_to_string(Shape::from_u8(chip.shape)?));
+                deal_commitment = Some(hex::encode(&round_state.deal_commitment));
+                chip = Some(round_state.player_b_chip.to_humanized()?);
                 let initial_hint = round_state.player_b_first_hint;
-                hint = Some(hint_to_string(Hint::from_u8(initial_hint)?));
+                hint = Some(Hint::from_u8(initial_hint)?);
                 if round_state.player_b_first_submit.is_some() {
-                    first_submit = Some(hint_to_string(Hint::from_u8(round_state.player_b_first_submit.unwrap())?));
+                    first_submit = Some(Hint::from_u8(round_state.player_b_first_submit.unwrap())?);
                     first_submit_block = round_state.player_b_first_submit_block;
                     // player cannot see opponent's submission until made own submission
                     if round_state.player_a_first_submit.is_some() {
                         if round_state.player_b_first_extra_secret.is_some() {
-                            first_extra_secret = Some(hint_to_string(Hint::from_u8(round_state.player_b_first_extra_secret.unwrap())?));
+                            first_extra_secret = Some(Hint::from_u8(round_state.player_b_first_extra_secret.unwrap())?);
                         } else {
-                            opponent_first_submit = Some(hint_to_string(Hint::from_u8(round_state.player_a_first_submit.unwrap())?));
+                            opponent_first_submit = Some(Hint::from_u8(round_state.player_a_first_submit.unwrap())?);
                         }
                         second_submit_turn_start_block = Some(max(
                             round_state.player_a_first_submit_block.unwrap(),
@@ -2082,14 +3432,14 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_b_second_submit.is_some() {
-                    second_submit = Some(hint_to_string(Hint::from_u8(round_state.player_b_second_submit.unwrap())?));
+                    second_submit = Some(Hint::from_u8(round_state.player_b_second_submit.unwrap())?);
                     second_submit_block = round_state.player_b_second_submit_block;
                     // player cannot see opponent's submission until made own submission
                     if round_state.player_a_second_submit.is_some() {
                         if round_state.player_b_second_extra_secret.is_some() {
-                            second_extra_secret = Some(hint_to_string(Hint::from_u8(round_state.player_b_second_extra_secret.unwrap())?));
+                            second_extra_secret = Some(Hint::from_u8(round_state.player_b_second_extra_secret.unwrap())?);
                         } else {
-                            opponent_second_submit = Some(hint_to_string(Hint::from_u8(round_state.player_a_second_submit.unwrap())?));
+                            opponent_second_submit = Some(Hint::from_u8(round_state.player_a_second_submit.unwrap())?);
                         }
                         guess_turn_start_block = Some(max(
                             round_state.player_a_second_submit_block.unwrap(),
@@ -2098,12 +3448,12 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_b_guess.is_some() {
-                    guess = Some(guess_to_string(round_state.player_b_guess.unwrap().to_humanized()?));
+                    guess = Some(round_state.player_b_guess.unwrap().to_humanized()?);
                     guess_block = round_state.player_b_guess_block;
                     // player cannot see opponent's guess until made own guess
                     if round_state.player_a_guess.is_some() {
-                        opponent_guess = Some(guess_to_string(round_state.player_a_guess.unwrap().to_humanized()?));
-                        if game_state.round == 3 {
+                        opponent_guess = Some(round_state.player_a_guess.unwrap().to_humanized()?);
+                        if in_reward_round {
                             // went to pick reward round, send block when started
                             pick_reward_round_start_block = Some(max(
                                 round_state.player_a_guess_block.unwrap(),
@@ -2113,24 +3463,30 @@ fn get_game_state_response<S: Storage>(
                     }
                 }
                 if round_state.player_b_round_result.is_some() {
-                    round_result = Some(round_result_to_string(RoundResult::from_u8(round_state.player_b_round_result.unwrap())?));
+                    round_result = Some(RoundResult::from_u8(round_state.player_b_round_result.unwrap())?);
                     // player cannot see opponent's round result until own round result if available
                     if round_state.player_a_round_result.is_some() {
-                        opponent_round_result = Some(round_result_to_string(RoundResult::from_u8(round_state.player_a_round_result.unwrap())?));
+                        opponent_round_result = Some(RoundResult::from_u8(round_state.player_a_round_result.unwrap())?);
                     }
                 }
             }
         }
     }
 
-    Ok(GameStateResponse {
+    Ok(GameStateV2Response {
         round,
+        rounds_per_game,
+        stakes,
+        private_game,
+        round_wins,
+        opponent_round_wins,
+        round_history,
         wager,
-        chip_color,
-        chip_shape,
+        chip,
         hint,
         powerup,
         first_round_start_block,
+        deal_commitment,
         first_submit,
         first_submit_block,
         opponent_first_submit,
@@ -2148,29 +3504,123 @@ fn get_game_state_response<S: Storage>(
         opponent_round_result,
         pick_reward_round_start_block,
         finished,
+        is_player_a,
         result,
         opponent_powerup,
         pick,
         jackpot_reward,
         nft_token_id,
+        reveal,
+    })
+}
+
+// the game state handle answers are given with, the v1 game state unless v2 is asked for
+fn game_state_answer<S: Storage>(
+    storage: &S,
+    player: CanonicalAddr,
+    v2: Option<bool>,
+) -> Result<(Option<GameStateResponse>, Option<GameStateV2Response>), ContractError> {
+    if v2.unwrap_or(false) {
+        Ok((None, Some(get_game_state_v2_response(storage, player)?)))
+    } else {
+        Ok((Some(get_game_state_response(storage, player)?), None))
+    }
+}
+
+// the v1 game state, the typed v2 game state with every value given in its string form
+fn get_game_state_response<S: Storage>(
+    storage: &S,
+    player: CanonicalAddr,
+) -> Result<GameStateResponse, ContractError> {
+    let state = get_game_state_v2_response(storage, player)?;
+
+    let round_history = state.round_history.map(|history| history.into_iter().map(|h| RoundHistoryResponse {
+        round: h.round,
+        chip_color: color_to_string(h.chip.color),
+        chip_shape: shape_to_string(h.chip.shape),
+        guess: h.guess.map(guess_to_string),
+        opponent_guess: h.opponent_guess.map(guess_to_string),
+        round_result: h.round_result.map(round_result_to_string),
+        opponent_round_result: h.opponent_round_result.map(round_result_to_string),
+    }).collect());
+    let reveal = state.reveal.map(|reveal| reveal.into_iter().map(|r| RoundRevealResponse {
+        round: r.round,
+        bag_chip_color: color_to_string(r.bag_chip.color),
+        bag_chip_shape: shape_to_string(r.bag_chip.shape),
+        opponent_chip_color: color_to_string(r.opponent_chip.color),
+        opponent_chip_shape: shape_to_string(r.opponent_chip.shape),
+        opponent_hint: hint_to_string(r.opponent_hint),
+        first_submit_provably_false: r.first_submit_provably_false,
+        second_submit_provably_false: r.second_submit_provably_false,
+        opponent_first_submit_provably_false: r.opponent_first_submit_provably_false,
+        opponent_second_submit_provably_false: r.opponent_second_submit_provably_false,
+    }).collect());
+    let is_player_a = state.is_player_a.unwrap_or(true);
+    let result = state.result.map(|game_result| game_result_to_string(game_result, is_player_a));
+
+    Ok(GameStateResponse {
+        round: state.round,
+        rounds_per_game: state.rounds_per_game,
+        stakes: state.stakes,
+        private_game: state.private_game,
+        round_wins: state.round_wins,
+        opponent_round_wins: state.opponent_round_wins,
+        round_history,
+        wager: state.wager,
+        chip_color: state.chip.as_ref().map(|chip| color_to_string(chip.color.clone())),
+        chip_shape: state.chip.map(|chip| shape_to_string(chip.shape)),
+        hint: state.hint.map(hint_to_string),
+        powerup: state.powerup,
+        first_round_start_block: state.first_round_start_block,
+        deal_commitment: state.deal_commitment,
+        first_submit: state.first_submit.map(hint_to_string),
+        first_submit_block: state.first_submit_block,
+        opponent_first_submit: state.opponent_first_submit.map(hint_to_string),
+        first_extra_secret: state.first_extra_secret.map(hint_to_string),
+        second_submit_turn_start_block: state.second_submit_turn_start_block,
+        second_submit: state.second_submit.map(hint_to_string),
+        second_submit_block: state.second_submit_block,
+        opponent_second_submit: state.opponent_second_submit.map(hint_to_string),
+        second_extra_secret: state.second_extra_secret.map(hint_to_string),
+        guess_turn_start_block: state.guess_turn_start_block,
+        guess: state.guess.map(guess_to_string),
+        guess_block: state.guess_block,
+        opponent_guess: state.opponent_guess.map(guess_to_string),
+        round_result: state.round_result.map(round_result_to_string),
+        opponent_round_result: state.opponent_round_result.map(round_result_to_string),
+        pick_reward_round_start_block: state.pick_reward_round_start_block,
+        finished: state.finished,
+        result,
+        opponent_powerup: state.opponent_powerup,
+        pick: state.pick,
+        jackpot_reward: state.jackpot_reward,
+        nft_token_id: state.nft_token_id,
+        reveal,
     })
 }
 
 fn query_game_state<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     account: &HumanAddr,
-) -> StdResult<Binary> {
+) -> Result<Binary, ContractError> {
     let player = deps.api.canonical_address(account)?;
     let game_state_response = get_game_state_response(&deps.storage, player)?;
 
     let response = QueryAnswer::GameState {
         round: game_state_response.round,
+        rounds_per_game: game_state_response.rounds_per_game,
+        stakes: game_state_response.stakes,
+        private_game: game_state_response.private_game,
+        round_wins: game_state_response.round_wins,
+        opponent_round_wins: game_state_response.opponent_round_wins,
+        round_history: game_state_response.round_history,
         wager: game_state_response.wager,
         chip_color: game_state_response.chip_color,
         chip_shape: game_state_response.chip_shape,
         hint: game_state_response.hint,
         powerup: game_state_response.powerup,
         first_round_start_block: game_state_response.first_round_start_block,
+        deal_commitment: game_state_response.deal_commitment,
         first_submit: game_state_response.first_submit,
         first_submit_block: game_state_response.first_submit_block,
         opponent_first_submit: game_state_response.opponent_first_submit,
@@ -2193,116 +3643,1512 @@ fn query_game_state<S: Storage, A: Api, Q: Querier>(
         pick: game_state_response.pick,
         jackpot_reward: game_state_response.jackpot_reward,
         nft_token_id: game_state_response.nft_token_id,
+        reveal: game_state_response.reveal,
     };
-    to_binary(&response)
+    Ok(to_binary(&response)?)
+}
+
+fn query_game_state_v2<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+) -> Result<Binary, ContractError> {
+    let player = deps.api.canonical_address(account)?;
+    let game_state = get_game_state_v2_response(&deps.storage, player)?;
+
+    Ok(to_binary(&QueryAnswer::GameStateV2 { game_state })?)
 }
 
 fn query_player_stats<S: Storage, A: Api, Q: Querier>(
-    _deps: &Extern<S, A, Q>,
-    _account: &HumanAddr,
-) -> StdResult<Binary> {
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+) -> Result<Binary, ContractError> {
+    let player = deps.api.canonical_address(account)?;
+    let stats = get_player_stats(&deps.storage, &player);
     let response = QueryAnswer::PlayerStats {
-        info: "TODO".to_string()
+        games_played: stats.games_played,
+        wins: stats.wins,
+        losses: stats.losses,
+        both_lose: stats.both_lose,
+        ties: stats.ties,
+        jackpots_won: stats.jackpots_won,
+        nfts_won: stats.nfts_won,
+        no_rewards: stats.no_rewards,
+        timeout_wins: stats.timeout_wins,
+        timeout_losses: stats.timeout_losses,
+        withdrawals: stats.withdrawals,
+        total_wagered: Uint128(stats.total_wagered),
+        total_payout: Uint128(stats.total_payout),
+        net_winnings: stats.net_winnings().to_string(),
     };
-    to_binary(&response)
+    Ok(to_binary(&response)?)
+}
+
+fn query_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+) -> Result<Binary, ContractError> {
+    let player = deps.api.canonical_address(account)?;
+    let config = get_config(&deps.storage)?;
+    let balances: Vec<Coin> = config.denoms.iter().map(|denom| Coin {
+        denom: denom.denom.clone(),
+        amount: Uint128(get_balance(&deps.storage, &player, &denom.denom)),
+    }).collect();
+    let response = QueryAnswer::Balance {
+        amount: balances[0].amount,
+        denom: balances[0].denom.clone(),
+        balances,
+    };
+    Ok(to_binary(&response)?)
+}
+
+// entries to skip and take for a page of a paged query, the page size is capped at MAX_PAGE_SIZE
+fn page_bounds(page: Option<u32>, page_size: Option<u32>) -> (u32, u32) {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    (page.unwrap_or(0).saturating_mul(page_size), page_size)
+}
+
+fn query_game_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let player = deps.api.canonical_address(account)?;
+    let (mut skip, page_size) = page_bounds(page, page_size);
+
+    // only the newest game can still be in progress, leave it out of the history
+    let (newest, mut total) = get_player_games(&deps.storage, &player, 0, 1)?;
+    if let Some(newest) = newest.first() {
+        if !get_game_state(&deps.storage, *newest)?.finished {
+            skip = skip.saturating_add(1);
+            total = total - 1;
+        }
+    }
+
+    let (game_idxs, _) = get_player_games(&deps.storage, &player, skip, page_size)?;
+    let mut games: Vec<GameSummaryResponse> = vec![];
+    for game_idx in game_idxs {
+        let game_state = get_game_state(&deps.storage, game_idx)?;
+        games.push(get_game_summary_response(game_idx, game_state, &player)?);
+    }
+
+    let response = QueryAnswer::GameHistory {
+        total,
+        games,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn query_pool_log<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let (skip, page_size) = page_bounds(page, page_size);
+
+    let (changes, total) = get_pool_log(&deps.storage, skip, page_size)?;
+    let changes: Result<Vec<PoolChangeResponse>, ContractError> = changes
+        .into_iter()
+        .map(pool_change_response)
+        .collect();
+
+    let response = QueryAnswer::PoolLog {
+        total,
+        changes: changes?,
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn pool_change_response(change: PoolChange) -> Result<PoolChangeResponse, ContractError> {
+    let reason = match PoolChangeReason::from_u8(change.reason)? {
+        PoolChangeReason::Seed => "seed",
+        PoolChangeReason::BothLose => "both_lose",
+        PoolChangeReason::Timeout => "timeout",
+        PoolChangeReason::Jackpot => "jackpot",
+        PoolChangeReason::Rake => "rake",
+        PoolChangeReason::Funding => "funding",
+        PoolChangeReason::Withdrawal => "withdrawal",
+    };
+    Ok(PoolChangeResponse {
+        denom: change.denom,
+        added: Uint128(change.added),
+        removed: Uint128(change.removed),
+        pool: Uint128(change.pool),
+        reason: reason.to_string(),
+        game_idx: change.game_idx,
+        block: change.block,
+    })
+}
+
+fn query_solvency<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> Result<Binary, ContractError> {
+    let config = get_config(&deps.storage)?;
+    let contract_address = deps.api.human_address(&config.contract_address)?;
+
+    let mut denoms: Vec<SolvencyResponse> = vec![];
+    for denom in &config.denoms {
+        let pool = get_pool(&deps.storage, &denom.denom)?;
+        let escrow = get_escrow(&deps.storage, &denom.denom);
+        let claimable = get_balance_total(&deps.storage, &denom.denom);
+        let liabilities = pool + escrow + claimable;
+
+        // the token balance is read with the viewing key set at init, native ones from the bank
+        let balance = match &config.token {
+            Some(token) if deps.api.human_address(&token.address)?.as_str() == denom.denom => {
+                balance_query(
+                    &deps.querier,
+                    contract_address.clone(),
+                    config.viewing_key.clone(),
+                    256,
+                    token.code_hash.clone(),
+                    deps.api.human_address(&token.address)?,
+                )?.amount.u128()
+            }
+            _ => deps.querier.query_balance(contract_address.clone(), &denom.denom)?.amount.u128(),
+        };
+
+        denoms.push(SolvencyResponse {
+            denom: denom.denom.clone(),
+            pool: Uint128(pool),
+            escrow: Uint128(escrow),
+            claimable: Uint128(claimable),
+            liabilities: Uint128(liabilities),
+            balance: Uint128(balance),
+            difference: (balance as i128 - liabilities as i128).to_string(),
+            solvent: balance >= liabilities,
+        });
+    }
+
+    Ok(to_binary(&QueryAnswer::Solvency { denoms })?)
+}
+
+fn game_summary_result(
+    game_state: &GameState,
+    is_player_a: bool,
+) -> Result<String, ContractError> {
+    if game_state.player_b.is_none() {
+        return Ok("withdrawn".to_string());
+    }
+    if let Some(timed_out_player) = &game_state.timed_out_player {
+        let player_timed_out = (*timed_out_player == game_state.player_a) == is_player_a;
+        if player_timed_out {
+            return Ok("you lost by timeout".to_string());
+        }
+        return Ok("you won by timeout".to_string());
+    }
+    let game_result = match game_state.result {
+        Some(result) => GameResult::from_u8(result)?,
+        // ended without being played out, wagers were refunded
+        None => return Ok("refunded".to_string()),
+    };
+    let result = match (game_result, is_player_a) {
+        (GameResult::AWon, true) | (GameResult::BWon, false) => "you won wager",
+        (GameResult::AWon, false) | (GameResult::BWon, true) => "you lost wager",
+        (GameResult::BothLose, _) => "both lost wager",
+        (GameResult::AJackpotBNft, true) | (GameResult::ANftBJackpot, false) => "you won jackpot",
+        (GameResult::AJackpotBNft, false) | (GameResult::ANftBJackpot, true) => "you won nft",
+        (GameResult::NoReward, _) if game_state.in_reward_round() => "you lost reward",
+        (GameResult::NoReward, _) => "tie",
+    };
+    Ok(result.to_string())
+}
+
+fn query_game_transcript<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+    game_idx: u32,
+) -> Result<Binary, ContractError> {
+    let player = deps.api.canonical_address(account)?;
+    let game_state = get_game_state(&deps.storage, game_idx)?;
+    let is_player_a = player == game_state.player_a;
+    if !is_player_a && game_state.player_b.as_ref() != Some(&player) {
+        return Err(ContractError::NotAPlayer {});
+    }
+
+    let finished = game_state.finished;
+    let name = |is_a: bool| if is_a == is_player_a { TranscriptPlayer::You } else { TranscriptPlayer::Opponent };
+    // opponent's moves are only shown once the game is finished
+    let shown = |is_a: bool| finished || is_a == is_player_a;
+
+    // events are sorted by block, events in the same block stay in the order they were added
+    let mut events: Vec<(u64, TranscriptEvent)> = vec![];
+    events.push((game_state.created_block, TranscriptEvent::Join {
+        player: name(true),
+        block: game_state.created_block,
+        wager: Uint128(game_state.player_a_wager.unwrap_or(0)),
+    }));
+    let rounds = game_state.rounds();
+    // player b joined when the first round was dealt
+    if let Some(first_round) = rounds.first() {
+        events.push((first_round.round_start_block, TranscriptEvent::Join {
+            player: name(false),
+            block: first_round.round_start_block,
+            wager: Uint128(game_state.player_b_wager.unwrap_or(0)),
+        }));
+    }
+
+    for (i, round_state) in rounds.iter().enumerate() {
+        let round = (i + 1) as u8;
+        // a provably false submission gives the other player an extra secret
+        let submits = [
+            (true, 1_u8, round_state.player_a_first_submit, round_state.player_a_first_submit_block, round_state.player_b_first_extra_secret),
+            (false, 1_u8, round_state.player_b_first_submit, round_state.player_b_first_submit_block, round_state.player_a_first_extra_secret),
+            (true, 2_u8, round_state.player_a_second_submit, round_state.player_a_second_submit_block, round_state.player_b_second_extra_secret),
+            (false, 2_u8, round_state.player_b_second_submit, round_state.player_b_second_submit_block, round_state.player_a_second_extra_secret),
+        ];
+        for &(is_a, turn, submit, block, extra_secret) in submits.iter() {
+            if let (Some(submit), Some(block)) = (submit, block) {
+                let hint = if shown(is_a) { Some(Hint::from_u8(submit)?) } else { None };
+                events.push((block, TranscriptEvent::Submit { player: name(is_a), round, turn, hint, block }));
+                if let Some(extra_secret) = extra_secret {
+                    let secret = if shown(!is_a) { Some(Hint::from_u8(extra_secret)?) } else { None };
+                    events.push((block, TranscriptEvent::ExtraSecret { player: name(!is_a), round, turn, secret, block }));
+                }
+            }
+        }
+
+        let guesses = [
+            (true, &round_state.player_a_guess, round_state.player_a_guess_block),
+            (false, &round_state.player_b_guess, round_state.player_b_guess_block),
+        ];
+        for &(is_a, guess, block) in guesses.iter() {
+            if let (Some(guess), Some(block)) = (guess, block) {
+                let guess = if shown(is_a) { Some(guess.to_humanized()?) } else { None };
+                events.push((block, TranscriptEvent::Guess { player: name(is_a), round, guess, block }));
+            }
+        }
+    }
+
+    let picks = [
+        (true, game_state.player_a_reward_pick, game_state.player_a_reward_pick_block),
+        (false, game_state.player_b_reward_pick, game_state.player_b_reward_pick_block),
+    ];
+    for &(is_a, pick, block) in picks.iter() {
+        if let (Some(pick), Some(block)) = (pick, block) {
+            let pick = if shown(is_a) { Some(pick_to_string(pick)) } else { None };
+            events.push((block, TranscriptEvent::RewardPick { player: name(is_a), pick, block }));
+        }
+    }
+
+    // the game ends with the timeout and the payouts
+    let finished_block = game_state.finished_block;
+    let end = finished_block.unwrap_or(u64::MAX);
+    if let Some(timed_out_player) = &game_state.timed_out_player {
+        events.push((end, TranscriptEvent::Timeout {
+            player: name(*timed_out_player == game_state.player_a),
+            block: finished_block,
+        }));
+    }
+    if finished {
+        let payouts = [(true, game_state.player_a_payout), (false, game_state.player_b_payout)];
+        for &(is_a, payout) in payouts.iter() {
+            if let Some(payout) = payout {
+                events.push((end, TranscriptEvent::Payout {
+                    player: name(is_a),
+                    amount: Uint128(payout),
+                    denom: game_state.denom.clone(),
+                    block: finished_block,
+                }));
+            }
+        }
+    }
+
+    events.sort_by_key(|(block, _)| *block);
+    let response = QueryAnswer::GameTranscript {
+        game_idx,
+        finished,
+        events: events.into_iter().map(|(_, event)| event).collect(),
+    };
+    Ok(to_binary(&response)?)
+}
+
+fn get_game_summary_response(
+    game_idx: u32,
+    game_state: GameState,
+    player: &CanonicalAddr,
+) -> Result<GameSummaryResponse, ContractError> {
+    let is_player_a = *player == game_state.player_a;
+    let round_outcomes = game_state.round_outcomes()?;
+    let player_a_round_wins = round_outcomes.iter().filter(|o| **o == RoundOutcome::AWon).count() as u8;
+    let player_b_round_wins = round_outcomes.iter().filter(|o| **o == RoundOutcome::BWon).count() as u8;
+
+    let mut rounds: Vec<RoundSummaryResponse> = vec![];
+    for (i, round_state) in game_state.rounds().into_iter().enumerate() {
+        let (chip, opponent_chip, own_guess, other_guess, own_result, other_result) = if is_player_a {
+            (round_state.player_a_chip, round_state.player_b_chip, round_state.player_a_guess,
+             round_state.player_b_guess, round_state.player_a_round_result, round_state.player_b_round_result)
+        } else {
+            (round_state.player_b_chip, round_state.player_a_chip, round_state.player_b_guess,
+             round_state.player_a_guess, round_state.player_b_round_result, round_state.player_a_round_result)
+        };
+        let guess = match own_guess {
+            Some(own_guess) => Some(guess_to_string(own_guess.to_humanized()?)),
+            None => None,
+        };
+        let opponent_guess = match other_guess {
+            Some(other_guess) => Some(guess_to_string(other_guess.to_humanized()?)),
+            None => None,
+        };
+        let round_result = match own_result {
+            Some(own_result) => Some(round_result_to_string(RoundResult::from_u8(own_result)?)),
+            None => None,
+        };
+        let opponent_round_result = match other_result {
+            Some(other_result) => Some(round_result_to_string(RoundResult::from_u8(other_result)?)),
+            None => None,
+        };
+        rounds.push(RoundSummaryResponse {
+            round: (i + 1) as u8,
+            bag_chip_color: color_to_string(Color::from_u8(round_state.bag_chip.color)?),
+            bag_chip_shape: shape_to_string(Shape::from_u8(round_state.bag_chip.shape)?),
+            chip_color: color_to_string(Color::from_u8(chip.color)?),
+            chip_shape: shape_to_string(Shape::from_u8(chip.shape)?),
+            opponent_chip_color: color_to_string(Color::from_u8(opponent_chip.color)?),
+            opponent_chip_shape: shape_to_string(Shape::from_u8(opponent_chip.shape)?),
+            guess,
+            opponent_guess,
+            round_result,
+            opponent_round_result,
+        });
+    }
+
+    let (wager, opponent_wager, payout, round_wins, opponent_round_wins) = if is_player_a {
+        (game_state.player_a_wager, game_state.player_b_wager, game_state.player_a_payout,
+         player_a_round_wins, player_b_round_wins)
+    } else {
+        (game_state.player_b_wager, game_state.player_a_wager, game_state.player_b_payout,
+         player_b_round_wins, player_a_round_wins)
+    };
+    let fees: Vec<FeeResponse> = game_state.fees.iter()
+        .filter(|fee| fee.payer == *player)
+        .map(|fee| FeeResponse {
+            amount: Uint128(fee.amount),
+            to_pool: Uint128(fee.to_pool),
+            to_treasury: Uint128(fee.to_treasury),
+            to_referral: Uint128(fee.to_referral),
+        })
+        .collect();
+
+    Ok(GameSummaryResponse {
+        game_idx,
+        stakes: stakes_to_string(StakesTier::from_u8(game_state.stakes)?),
+        private_game: game_state.is_private(),
+        result: game_summary_result(&game_state, is_player_a)?,
+        denom: game_state.denom.clone(),
+        wager: Uint128(wager.unwrap_or(0)),
+        opponent_wager: opponent_wager.map(Uint128),
+        payout: Uint128(payout.unwrap_or(0)),
+        fees,
+        round_wins,
+        opponent_round_wins,
+        rounds,
+        jackpot_reward: game_state.jackpot_reward.map(Uint128),
+        nft_token_id: game_state.nft_token_id,
+    })
 }
 
-/*
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{coins,};
-    use crate::msg::{InitMsg};
-    use crate::random::{get_random_color,};
+    use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+    use cosmwasm_std::{coins, from_slice, QuerierResult};
+    use crate::msg::JackpotTier;
+    use serde::Serialize;
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies(20, &[]);
+    type TestDeps = Extern<MockStorage, MockApi, MinterQuerier>;
+
+    const HEIGHT: u64 = 12345;
+    const LOW: u128 = 1000000;
+    const MEDIUM: u128 = 5000000;
+
+    // answers every query as the minter's private metadata query, with the description of the
+    // powerup nft
+    struct MinterQuerier {
+        description: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct PrivateMetadataAnswer {
+        private_metadata: Metadata,
+    }
+
+    impl Querier for MinterQuerier {
+        fn raw_query(&self, _bin_request: &[u8]) -> QuerierResult {
+            let extension = Extension {
+                name: None,
+                description: self.description.clone(),
+                image: None,
+                background_color: None,
+                image_data: None,
+                attributes: None,
+                animation_url: None,
+                youtube_url: None,
+                external_url: None,
+                media: None,
+                protected_attributes: None,
+            };
+            let answer = PrivateMetadataAnswer {
+                private_metadata: Metadata { token_uri: None, extension: Some(extension) },
+            };
+            Ok(to_binary(&answer))
+        }
+    }
+
+    // how a player guesses in play_round
+    #[derive(Clone, Copy)]
+    enum Play {
+        Opponent,
+        WrongBag,
+        Abstain,
+    }
+
+    impl Play {
+        fn guess(self, own_chip: &Chip, opponent_chip: &Chip) -> Guess {
+            match self {
+                Play::Opponent => Guess {
+                    target: Target::Opponent,
+                    color: Some(opponent_chip.color.clone()),
+                    shape: Some(opponent_chip.shape.clone()),
+                },
+                // the bag chip never shares a color or shape with a player's chip
+                Play::WrongBag => Guess {
+                    target: Target::Bag,
+                    color: Some(own_chip.color.clone()),
+                    shape: Some(own_chip.shape.clone()),
+                },
+                Play::Abstain => Guess { target: Target::Abstain, color: None, shape: None },
+            }
+        }
+    }
 
-        let msg = InitMsg {
+    fn init_msg(rounds_per_game: u8) -> InitMsg {
+        InitMsg {
             admin: None,
-            rounds_per_game: 2,
-            red_weight: Some(25),
-            blue_weight: Some(25),
-            green_weight: Some(25),
-            black_weight: Some(25),
-            triangle_weight: Some(25),
-            square_weight: Some(25),
-            circle_weight: Some(25),
-            star_weight: Some(25),
-            stakes: Some(Uint128(1000000)),
-            timeout: Some(20),
-            entropy: "random".to_string(),
+            rounds_per_game,
+            red_weight: None,
+            green_weight: None,
+            blue_weight: None,
+            black_weight: None,
+            triangle_weight: None,
+            square_weight: None,
+            circle_weight: None,
+            star_weight: None,
+            low_stakes: None,
+            medium_stakes: None,
+            high_stakes: None,
+            denoms: None,
+            timeout: None,
+            lobby_policy: None,
+            lobby_expiration: None,
+            minter: ContractInfo {
+                code_hash: "minter_hash".to_string(),
+                address: HumanAddr::from("minter"),
+            },
+            token: None,
+            jackpot_rule: None,
+            pool_withdrawal_delay: None,
+            entropy: "entropy".to_string(),
+        }
+    }
+
+    fn no_config_update() -> ConfigUpdate {
+        ConfigUpdate {
+            red_weight: None,
+            green_weight: None,
+            blue_weight: None,
+            black_weight: None,
+            triangle_weight: None,
+            square_weight: None,
+            circle_weight: None,
+            star_weight: None,
+            low_stakes: None,
+            medium_stakes: None,
+            high_stakes: None,
+            denoms: None,
+            timeout: None,
+            rounds_per_game: None,
+            lobby_policy: None,
+            lobby_expiration: None,
+        }
+    }
+
+    // contract initialized by "admin", with pool seeded into the uscrt jackpot pool
+    fn setup(rounds_per_game: u8, pool: u128) -> TestDeps {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: MinterQuerier { description: None },
         };
-        let env = mock_env("creator", &coins(1000, DENOM));
+        let funds = if pool > 0 { coins(pool, DENOM) } else { vec![] };
+        init(&mut deps, mock_env("admin", &funds), init_msg(rounds_per_game)).unwrap();
+        deps
+    }
+
+    fn handle_at(deps: &mut TestDeps, sender: &str, amount: u128, height: u64, msg: HandleMsg) -> StdResult<HandleResponse> {
+        let funds = if amount > 0 { coins(amount, DENOM) } else { vec![] };
+        let mut env = mock_env(sender, &funds);
+        env.block.height = height;
+        handle(deps, env, msg)
+    }
+
+    fn join_msg(stakes: Option<&str>, referrer: Option<&str>) -> HandleMsg {
+        HandleMsg::Join {
+            stakes: stakes.map(String::from),
+            entropy: None,
+            entropy_commitment: None,
+            from_balance: None,
+            denom: None,
+            referrer: referrer.map(HumanAddr::from),
+            v2: None,
+            padding: None,
+        }
+    }
+
+    fn join(deps: &mut TestDeps, player: &str, stakes: Option<&str>, amount: u128, height: u64) -> StdResult<HandleResponse> {
+        handle_at(deps, player, amount, height, join_msg(stakes, None))
+    }
+
+    fn submit(deps: &mut TestDeps, player: &str, hint: Hint, height: u64) -> StdResult<HandleResponse> {
+        handle_at(deps, player, 0, height, HandleMsg::SubmitV2 { hint, padding: None })
+    }
+
+    fn guess(deps: &mut TestDeps, player: &str, guess: Guess, height: u64) -> StdResult<HandleResponse> {
+        handle_at(deps, player, 0, height, HandleMsg::GuessV2 { guess, padding: None })
+    }
+
+    fn pick_reward(deps: &mut TestDeps, player: &str, reward: &str, height: u64) -> StdResult<HandleResponse> {
+        handle_at(deps, player, 0, height, HandleMsg::PickReward { reward: reward.to_string(), v2: None, padding: None })
+    }
+
+    fn canonical(deps: &TestDeps, player: &str) -> CanonicalAddr {
+        deps.api.canonical_address(&HumanAddr::from(player)).unwrap()
+    }
+
+    fn current_game(deps: &TestDeps, player: &str) -> u32 {
+        get_current_game(&deps.storage, &canonical(deps, player)).unwrap()
+    }
+
+    fn game(deps: &TestDeps, player: &str) -> GameState {
+        get_game_state(&deps.storage, current_game(deps, player)).unwrap()
+    }
+
+    fn balance(deps: &TestDeps, player: &str) -> u128 {
+        get_balance(&deps.storage, &canonical(deps, player), DENOM)
+    }
+
+    fn pool(deps: &TestDeps) -> u128 {
+        get_pool(&deps.storage, DENOM).unwrap()
+    }
+
+    fn stats(deps: &TestDeps, player: &str) -> PlayerStats {
+        get_player_stats(&deps.storage, &canonical(deps, player))
+    }
+
+    fn state_v2(deps: &TestDeps, player: &str) -> GameStateV2Response {
+        get_game_state_v2_response(&deps.storage, canonical(deps, player)).unwrap()
+    }
+
+    fn transcript(deps: &TestDeps, player: &str, game_idx: u32) -> (bool, Vec<TranscriptEvent>) {
+        let answer = query_game_transcript(deps, &HumanAddr::from(player), game_idx).unwrap();
+        match from_binary(&answer).unwrap() {
+            QueryAnswer::GameTranscript { finished, events, .. } => (finished, events),
+            _ => panic!("expected a game transcript"),
+        }
+    }
+
+    fn submitted_hints(events: &[TranscriptEvent]) -> Vec<(TranscriptPlayer, Option<Hint>)> {
+        events.iter().filter_map(|event| match event {
+            TranscriptEvent::Submit { player, hint, .. } => Some((*player, hint.clone())),
+            _ => None,
+        }).collect()
+    }
+
+    // the code of a contract error returned by handle
+    fn error_code(result: StdResult<HandleResponse>) -> u16 {
+        match result {
+            Err(StdError::GenericErr { msg, .. }) => {
+                let code = msg.trim_start_matches("{\"code\":");
+                code[..code.find(',').unwrap()].parse().unwrap()
+            },
+            Err(error) => panic!("expected a contract error, got {:?}", error),
+            Ok(_) => panic!("expected a contract error"),
+        }
+    }
 
-        // we can just call .unwrap() to assert this was a success
-        let res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    // alice and bob join a game in the stakes tier, alice is player a
+    fn start_game(deps: &mut TestDeps, stakes: Option<&str>, height: u64) -> u32 {
+        let wager = if stakes == Some("practice") { 0 } else { LOW };
+        join(deps, "alice", stakes, wager, height).unwrap();
+        join(deps, "bob", stakes, wager, height).unwrap();
+        current_game(deps, "alice")
+    }
 
-        // it worked, let's query the state
-        //let res = query(&deps, QueryMsg::PlayerStats {}).unwrap();
-        //let value: CountResponse = from_binary(&res).unwrap();
-        //assert_eq!(17, value.count);
+    // both players submit true hints, then guess as given
+    fn play_round(deps: &mut TestDeps, height: u64, a: Play, b: Play) {
+        let round_state = game(deps, "alice").round_state.unwrap();
+        let a_chip = round_state.player_a_chip.to_humanized().unwrap();
+        let b_chip = round_state.player_b_chip.to_humanized().unwrap();
+        submit(deps, "alice", Hint::i_have_from_color(a_chip.color.clone()), height).unwrap();
+        submit(deps, "bob", Hint::i_have_from_color(b_chip.color.clone()), height).unwrap();
+        submit(deps, "alice", Hint::from_u8(round_state.player_a_first_hint).unwrap(), height).unwrap();
+        submit(deps, "bob", Hint::from_u8(round_state.player_b_first_hint).unwrap(), height).unwrap();
+        guess(deps, "alice", a.guess(&a_chip, &b_chip), height).unwrap();
+        guess(deps, "bob", b.guess(&b_chip, &a_chip), height).unwrap();
     }
 
     #[test]
-    fn random_color_shape() {
-        let mut deps = mock_dependencies(20, &[]);
-        let env = mock_env("creator", &coins(1000, DENOM));
+    fn test_game_outcome_tie_break() {
+        assert_eq!(game_outcome(&[RoundOutcome::AWon, RoundOutcome::BothLose, RoundOutcome::BothLose]), RoundOutcome::AWon);
+        assert_eq!(game_outcome(&[RoundOutcome::BWon, RoundOutcome::Tie]), RoundOutcome::BWon);
+
+        assert_eq!(game_outcome(&[RoundOutcome::BothLose]), RoundOutcome::BothLose);
+        assert_eq!(game_outcome(&[RoundOutcome::AWon, RoundOutcome::BWon, RoundOutcome::BothLose]), RoundOutcome::BothLose);
+        assert_eq!(game_outcome(&[RoundOutcome::BothLose, RoundOutcome::BothLose, RoundOutcome::Tie]), RoundOutcome::BothLose);
+
+        // as many rounds lost by both as tied goes to the reward round
+        assert_eq!(game_outcome(&[RoundOutcome::Tie]), RoundOutcome::Tie);
+        assert_eq!(game_outcome(&[RoundOutcome::BothLose, RoundOutcome::Tie]), RoundOutcome::Tie);
+        assert_eq!(game_outcome(&[RoundOutcome::AWon, RoundOutcome::BWon]), RoundOutcome::Tie);
+        assert_eq!(game_outcome(&[RoundOutcome::AWon, RoundOutcome::BWon, RoundOutcome::BothLose, RoundOutcome::Tie]), RoundOutcome::Tie);
+    }
 
-        let msg = InitMsg {
-            admin: None,
-            rounds_per_game: 2,
-            red_weight: Some(25),
-            blue_weight: Some(25),
-            green_weight: Some(25),
-            black_weight: Some(25),
-            triangle_weight: Some(25),
-            square_weight: Some(25),
-            circle_weight: Some(25),
-            star_weight: Some(25),
-            stakes: Some(Uint128(1000000)),
-            timeout: Some(20),
-            entropy: "random".to_string(),
+    #[test]
+    fn test_multi_round_game() {
+        let mut deps = setup(3, 0);
+        start_game(&mut deps, None, HEIGHT);
+
+        play_round(&mut deps, HEIGHT + 1, Play::Opponent, Play::Abstain);
+        let game_state = game(&deps, "alice");
+        assert!(!game_state.finished);
+        assert_eq!(game_state.round, 2);
+        assert_eq!(game_state.round_history.len(), 1);
+
+        play_round(&mut deps, HEIGHT + 2, Play::Abstain, Play::Opponent);
+        assert_eq!(game(&deps, "alice").round, 3);
+        let state = state_v2(&deps, "alice");
+        assert_eq!(state.round_wins, Some(1));
+        assert_eq!(state.opponent_round_wins, Some(1));
+
+        play_round(&mut deps, HEIGHT + 3, Play::Opponent, Play::WrongBag);
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.result, Some(GameResult::AWon.u8_val()));
+        assert_eq!(game_state.round_history.len(), 2);
+        assert_eq!(balance(&deps, "alice"), 2 * LOW);
+        let state = state_v2(&deps, "bob");
+        assert_eq!(state.round_wins, Some(1));
+        assert_eq!(state.opponent_round_wins, Some(2));
+        assert_eq!(state.result, Some(GameResult::AWon));
+        assert_eq!(state.reveal.map(|reveal| reveal.len()), Some(3));
+    }
+
+    #[test]
+    fn test_parse_stakes() {
+        assert_eq!(parse_stakes(None).unwrap(), StakesTier::Low);
+        assert_eq!(parse_stakes(Some("low".to_string())).unwrap(), StakesTier::Low);
+        assert_eq!(parse_stakes(Some("medium".to_string())).unwrap(), StakesTier::Medium);
+        assert_eq!(parse_stakes(Some("high".to_string())).unwrap(), StakesTier::High);
+        assert_eq!(parse_stakes(Some("practice".to_string())).unwrap(), StakesTier::Practice);
+        assert_eq!(parse_stakes(Some("free".to_string())).unwrap_err().code(), 3005);
+    }
+
+    #[test]
+    fn test_stakes_tiers_pair_separately() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", Some("low"), LOW, HEIGHT).unwrap();
+        join(&mut deps, "bob", Some("medium"), MEDIUM, HEIGHT).unwrap();
+        assert_eq!(error_code(join(&mut deps, "carol", Some("high"), MEDIUM, HEIGHT)), 2007);
+        assert_ne!(current_game(&deps, "alice"), current_game(&deps, "bob"));
+        assert_eq!(game(&deps, "bob").round, 0);
+        assert_eq!(game(&deps, "bob").player_a_wager, Some(MEDIUM));
+
+        join(&mut deps, "carol", Some("medium"), MEDIUM, HEIGHT + 1).unwrap();
+        assert_eq!(current_game(&deps, "carol"), current_game(&deps, "bob"));
+        assert_eq!(game(&deps, "alice").round, 0);
+    }
+
+    #[test]
+    fn test_practice_tie_has_no_reward_round() {
+        let mut deps = setup(1, 10000000);
+        start_game(&mut deps, Some("practice"), HEIGHT);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+        play_round(&mut deps, HEIGHT + 1, Play::Abstain, Play::Abstain);
+
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.round, 1);
+        assert_eq!(game_state.result, Some(GameResult::NoReward.u8_val()));
+        assert_eq!(stats(&deps, "alice").ties, 1);
+        assert_eq!(stats(&deps, "bob").ties, 1);
+        assert_eq!(balance(&deps, "alice"), 0);
+        assert_eq!(pool(&deps), 10000000);
+    }
+
+    #[test]
+    fn test_private_game() {
+        let mut deps = setup(1, 0);
+        let private_game = |opponent: Option<&str>, invite_code: Option<&str>| HandleMsg::CreatePrivateGame {
+            stakes: None,
+            opponent: opponent.map(HumanAddr::from),
+            invite_code: invite_code.map(String::from),
+            entropy: None,
+            entropy_commitment: None,
+            from_balance: None,
+            denom: None,
+            referrer: None,
+            v2: None,
+            padding: None,
+        };
+        let join_private_game = |host: &str, invite_code: Option<&str>| HandleMsg::JoinPrivateGame {
+            host: HumanAddr::from(host),
+            invite_code: invite_code.map(String::from),
+            entropy: None,
+            entropy_commitment: None,
+            from_balance: None,
+            denom: None,
+            referrer: None,
+            v2: None,
+            padding: None,
         };
-        let _res = init(&mut deps, env.clone(), msg).unwrap();
 
-        let msg = HandleMsg::Join { padding: None, };
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, private_game(None, None))), 3006);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, private_game(Some("alice"), None))), 3007);
+        handle_at(&mut deps, "alice", LOW, HEIGHT, private_game(None, Some("secret"))).unwrap();
+        assert!(game(&deps, "alice").is_private());
+        assert!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).is_empty());
+
+        // a public join does not pair with the private game
+        join(&mut deps, "bob", None, LOW, HEIGHT).unwrap();
+        assert_ne!(current_game(&deps, "bob"), current_game(&deps, "alice"));
+
+        assert_eq!(error_code(handle_at(&mut deps, "carol", LOW, HEIGHT, join_private_game("alice", None))), 3011);
+        assert_eq!(error_code(handle_at(&mut deps, "carol", LOW, HEIGHT, join_private_game("alice", Some("guess")))), 3011);
+        handle_at(&mut deps, "carol", LOW, HEIGHT, join_private_game("alice", Some("secret"))).unwrap();
+        assert_eq!(current_game(&deps, "carol"), current_game(&deps, "alice"));
+        assert_eq!(game(&deps, "alice").round, 1);
+        assert_eq!(error_code(handle_at(&mut deps, "dave", LOW, HEIGHT, join_private_game("alice", Some("secret")))), 3009);
+
+        handle_at(&mut deps, "dave", LOW, HEIGHT, private_game(Some("erin"), None)).unwrap();
+        assert_eq!(error_code(handle_at(&mut deps, "frank", LOW, HEIGHT, join_private_game("dave", None))), 3010);
+        handle_at(&mut deps, "erin", LOW, HEIGHT, join_private_game("dave", None)).unwrap();
+        assert_eq!(game(&deps, "erin").round, 1);
+    }
 
-        let mut fresh_entropy = to_binary(&msg).unwrap().0;
-        fresh_entropy.extend(to_binary(&env).unwrap().0);
-        println!("{:?}", fresh_entropy);
+    #[test]
+    fn test_join_pairs_players_and_escrows_wagers() {
+        let mut deps = setup(1, 0);
+
+        join(&mut deps, "alice", Some("low"), LOW, HEIGHT).unwrap();
+        let game_idx = current_game(&deps, "alice");
+        assert_eq!(game(&deps, "alice").round, 0);
+        assert_eq!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).len(), 1);
+        assert_eq!(get_escrow(&deps.storage, DENOM), LOW);
+
+        join(&mut deps, "bob", None, LOW, HEIGHT + 1).unwrap();
+        assert_eq!(current_game(&deps, "bob"), game_idx);
+        let game_state = game(&deps, "bob");
+        assert_eq!(game_state.round, 1);
+        assert_eq!(game_state.player_b, Some(canonical(&deps, "bob")));
+        assert!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).is_empty());
+        assert_eq!(get_escrow(&deps.storage, DENOM), 2 * LOW);
+
+        assert_eq!(error_code(join(&mut deps, "alice", None, LOW, HEIGHT + 2)), 3001);
+        assert_eq!(error_code(join(&mut deps, "carol", None, LOW - 1, HEIGHT + 2)), 2007);
+        assert_eq!(error_code(join(&mut deps, "carol", Some("practice"), LOW, HEIGHT + 2)), 2006);
+        assert_eq!(error_code(join(&mut deps, "carol", Some("free"), LOW, HEIGHT + 2)), 3005);
+        let msg = HandleMsg::Join {
+            stakes: None,
+            entropy: None,
+            entropy_commitment: None,
+            from_balance: None,
+            denom: Some("uatom".to_string()),
+            referrer: None,
+            v2: None,
+            padding: None,
+        };
+        assert_eq!(error_code(handle_at(&mut deps, "carol", 0, HEIGHT + 2, msg)), 2001);
+        assert_eq!(error_code(handle_at(&mut deps, "carol", LOW, HEIGHT + 2, join_msg(None, Some("carol")))), 3014);
+    }
 
-        supply_more_entropy(&mut deps.storage, fresh_entropy.as_slice()).unwrap();
-        let mut color_options = vec![
-            Color::Red,
-            Color::Green,
-            Color::Blue,
-            Color::Black,
-        ];
-        println!("{:?}", color_options);
-        
-        let color = get_random_color(&deps.storage, &mut color_options, true);
-        println!("{:?}", color);
+    #[test]
+    fn test_lobby_game_expires() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", None, LOW, HEIGHT).unwrap();
+        let expired_idx = current_game(&deps, "alice");
+
+        join(&mut deps, "bob", None, LOW, HEIGHT + DEFAULT_LOBBY_EXPIRATION).unwrap();
+        let expired_game = get_game_state(&deps.storage, expired_idx).unwrap();
+        assert!(expired_game.finished);
+        assert_eq!(expired_game.result, None);
+        assert_eq!(expired_game.player_a_payout, Some(LOW));
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_ne!(current_game(&deps, "bob"), expired_idx);
+        assert_eq!(game(&deps, "bob").round, 0);
+        assert_eq!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).len(), 1);
+        assert_eq!(get_escrow(&deps.storage, DENOM), LOW);
+
+        join(&mut deps, "alice", None, LOW, HEIGHT + DEFAULT_LOBBY_EXPIRATION).unwrap();
+        assert_eq!(current_game(&deps, "alice"), current_game(&deps, "bob"));
+    }
 
-        let mut fresh_entropy = to_binary(&msg).unwrap().0;
-        fresh_entropy.extend(to_binary(&env).unwrap().0);
-        println!("{:?}", fresh_entropy);
+    #[test]
+    fn test_rejoin_after_own_game_expires() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", None, LOW, HEIGHT).unwrap();
+        let expired_idx = current_game(&deps, "alice");
+        assert_eq!(error_code(join(&mut deps, "alice", None, LOW, HEIGHT + DEFAULT_LOBBY_EXPIRATION - 1)), 3001);
+
+        join(&mut deps, "alice", None, LOW, HEIGHT + DEFAULT_LOBBY_EXPIRATION).unwrap();
+        assert!(get_game_state(&deps.storage, expired_idx).unwrap().finished);
+        assert_ne!(current_game(&deps, "alice"), expired_idx);
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), LOW);
+        assert_eq!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).len(), 1);
+    }
 
-        supply_more_entropy(&mut deps.storage, fresh_entropy.as_slice()).unwrap();
+    #[test]
+    fn test_lobby_expiration_fixed_when_game_created() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", None, LOW, HEIGHT).unwrap();
+        let waiting_idx = current_game(&deps, "alice");
+        let msg = HandleMsg::UpdateConfig {
+            config: ConfigUpdate { lobby_expiration: Some(10), ..no_config_update() },
+            padding: None,
+        };
+        handle_at(&mut deps, "admin", 0, HEIGHT + 1, msg).unwrap();
 
-        let mut color_options = vec![
-            Color::Red,
-            Color::Green,
-            Color::Blue,
-            Color::Black,
-        ];
-        println!("{:?}", color_options);
-        
-        let color = get_random_color(&deps.storage, &mut color_options, true);
-        println!("{:?}", color);
+        join(&mut deps, "bob", None, LOW, HEIGHT + 10).unwrap();
+        assert_eq!(current_game(&deps, "bob"), waiting_idx);
+
+        join(&mut deps, "carol", None, LOW, HEIGHT + 11).unwrap();
+        assert_eq!(error_code(join(&mut deps, "carol", None, LOW, HEIGHT + 20)), 3001);
+        join(&mut deps, "carol", None, LOW, HEIGHT + 21).unwrap();
+        assert_eq!(balance(&deps, "carol"), LOW);
+    }
+
+    #[test]
+    fn test_admin_config_checks() {
+        let mut deps = setup(1, 0);
+        let update = |config: ConfigUpdate| HandleMsg::UpdateConfig { config, padding: None };
+
+        assert_eq!(error_code(handle_at(&mut deps, "bob", 0, HEIGHT, HandleMsg::SetRake { rake_bps: 100, padding: None })), 1001);
+        assert_eq!(error_code(handle_at(&mut deps, "bob", 0, HEIGHT, update(no_config_update()))), 1001);
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, HandleMsg::SetRake { rake_bps: MAX_RAKE_BPS + 1, padding: None })), 1011);
+        let msg = HandleMsg::SetFeeRouting { pool_bps: 5000, treasury_bps: 3000, referral_bps: 1000, treasury: None, padding: None };
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, msg)), 1012);
+        let msg = HandleMsg::SetJackpotRule { rule: JackpotRule::Fraction { bps: 20000 }, padding: None };
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, msg)), 1017);
+        let msg = update(ConfigUpdate { rounds_per_game: Some(MAX_ROUNDS_PER_GAME + 1), ..no_config_update() });
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, msg)), 1009);
+        let msg = update(ConfigUpdate { low_stakes: Some(Uint128(2 * MEDIUM)), ..no_config_update() });
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, msg)), 1007);
+
+        let msg = HandleMsg::SetContractStatus { level: ContractStatus::StopTransactions, padding: None };
+        handle_at(&mut deps, "admin", 0, HEIGHT, msg).unwrap();
+        assert_eq!(error_code(join(&mut deps, "alice", None, LOW, HEIGHT)), 1003);
+    }
+
+    #[test]
+    fn test_stakes_change_keeps_open_games() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", Some("low"), LOW, HEIGHT).unwrap();
+        let msg = HandleMsg::UpdateConfig {
+            config: ConfigUpdate { low_stakes: Some(Uint128(2 * LOW)), ..no_config_update() },
+            padding: None,
+        };
+        handle_at(&mut deps, "admin", 0, HEIGHT + 1, msg).unwrap();
+
+        // games opened before the stakes changed keep their wager and are not paired
+        assert_eq!(error_code(join(&mut deps, "bob", Some("low"), LOW, HEIGHT + 2)), 2007);
+        join(&mut deps, "bob", Some("low"), 2 * LOW, HEIGHT + 2).unwrap();
+        assert_ne!(current_game(&deps, "bob"), current_game(&deps, "alice"));
+        assert_eq!(game(&deps, "alice").player_a_wager, Some(LOW));
+        assert_eq!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).len(), 2);
+    }
+
+    #[test]
+    fn test_claim_refund_when_stopped() {
+        let mut deps = setup(1, 0);
+        let game_idx = start_game(&mut deps, None, HEIGHT);
+        let claim_refund = || HandleMsg::ClaimRefund { v2: None, padding: None };
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT, claim_refund())), 4023);
+
+        let msg = HandleMsg::SetContractStatus { level: ContractStatus::StopAll, padding: None };
+        handle_at(&mut deps, "admin", 0, HEIGHT, msg).unwrap();
+        assert_eq!(error_code(submit(&mut deps, "alice", Hint::IHaveRed, HEIGHT + 1)), 1003);
+
+        handle_at(&mut deps, "bob", 0, HEIGHT + 1, claim_refund()).unwrap();
+        let game_state = get_game_state(&deps.storage, game_idx).unwrap();
+        assert!(game_state.finished);
+        assert_eq!(game_state.result, None);
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(balance(&deps, "bob"), LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT + 1, claim_refund())), 4024);
+    }
+
+    #[test]
+    fn test_force_endgame_after_timeout() {
+        let mut deps = setup(1, 0);
+        start_game(&mut deps, None, HEIGHT);
+        let force_endgame = || HandleMsg::ForceEndgame { v2: None, padding: None };
+        let a_chip = game(&deps, "alice").round_state.unwrap().player_a_chip.to_humanized().unwrap();
+        submit(&mut deps, "alice", Hint::i_have_from_color(a_chip.color), HEIGHT + 1).unwrap();
+
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT + DEFAULT_TIMEOUT - 1, force_endgame())), 4020);
+        assert_eq!(error_code(handle_at(&mut deps, "bob", 0, HEIGHT + DEFAULT_TIMEOUT, force_endgame())), 4020);
+        handle_at(&mut deps, "alice", 0, HEIGHT + DEFAULT_TIMEOUT, force_endgame()).unwrap();
+
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.timed_out_player, Some(canonical(&deps, "bob")));
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(pool(&deps), LOW);
+        assert_eq!(stats(&deps, "alice").timeout_wins, 1);
+        assert_eq!(stats(&deps, "bob").timeout_losses, 1);
+    }
+
+    #[test]
+    fn test_leaderboard_opt_out_leaves_the_list() {
+        let mut deps = setup(1, 0);
+        let opt_in = |opt_in: bool, display_name: Option<&str>| HandleMsg::SetLeaderboardOptIn {
+            opt_in,
+            display_name: display_name.map(String::from),
+            padding: None,
+        };
+        let leaderboard = |deps: &TestDeps| -> Vec<(HumanAddr, Option<String>)> {
+            match from_binary(&query_leaderboard(deps, None, None, None).unwrap()).unwrap() {
+                QueryAnswer::Leaderboard { players, .. } => {
+                    players.into_iter().map(|player| (player.player, player.display_name)).collect()
+                },
+                _ => panic!("expected the leaderboard"),
+            }
+        };
+        let player = |name: &str, display_name: Option<&str>| (HumanAddr::from(name), display_name.map(String::from));
+
+        handle_at(&mut deps, "alice", 0, HEIGHT, opt_in(true, Some("ace"))).unwrap();
+        handle_at(&mut deps, "bob", 0, HEIGHT, opt_in(true, None)).unwrap();
+        handle_at(&mut deps, "carol", 0, HEIGHT, opt_in(true, Some("cee"))).unwrap();
+        assert_eq!(leaderboard(&deps).len(), 3);
+
+        // the last player takes the place of the player who opted out
+        handle_at(&mut deps, "alice", 0, HEIGHT, opt_in(false, None)).unwrap();
+        assert_eq!(leaderboard(&deps), vec![player("carol", Some("cee")), player("bob", None)]);
+        handle_at(&mut deps, "carol", 0, HEIGHT, opt_in(true, Some("sea"))).unwrap();
+        assert_eq!(leaderboard(&deps), vec![player("carol", Some("sea")), player("bob", None)]);
+
+        handle_at(&mut deps, "carol", 0, HEIGHT, opt_in(false, None)).unwrap();
+        assert_eq!(leaderboard(&deps), vec![player("bob", None)]);
+        handle_at(&mut deps, "bob", 0, HEIGHT, opt_in(false, None)).unwrap();
+        assert!(leaderboard(&deps).is_empty());
+        assert!(get_leaderboard(&deps.storage).unwrap().is_empty());
+
+        handle_at(&mut deps, "alice", 0, HEIGHT, opt_in(false, None)).unwrap();
+        handle_at(&mut deps, "alice", 0, HEIGHT, opt_in(true, None)).unwrap();
+        assert_eq!(leaderboard(&deps), vec![player("alice", None)]);
+    }
+
+    #[test]
+    fn test_join_entropy_commitment() {
+        let mut deps = setup(1, 0);
+        let join_with_entropy = |entropy: Option<&str>, entropy_commitment: Option<String>| HandleMsg::Join {
+            stakes: None,
+            entropy: entropy.map(String::from),
+            entropy_commitment,
+            from_balance: None,
+            denom: None,
+            referrer: None,
+            v2: None,
+            padding: None,
+        };
+        let commitment = hex::encode(sha_256(b"alice words"));
+
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, join_with_entropy(None, Some(commitment.clone())))), 3002);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, join_with_entropy(Some(""), None))), 3003);
+        let msg = join_with_entropy(Some("other words"), Some(commitment.clone()));
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, msg)), 3004);
+
+        let msg = join_with_entropy(Some("alice words"), Some(commitment.to_uppercase()));
+        handle_at(&mut deps, "alice", LOW, HEIGHT, msg).unwrap();
+        handle_at(&mut deps, "bob", LOW, HEIGHT, join_with_entropy(Some("bob words"), None)).unwrap();
+        let game_state = game(&deps, "alice");
+        assert_eq!(game_state.player_a_entropy, Some(sha_256(b"alice words").to_vec()));
+        assert_eq!(game_state.player_b_entropy, Some(sha_256(b"bob words").to_vec()));
+    }
+
+    #[test]
+    fn test_both_lose_wagers_go_to_pool() {
+        let mut deps = setup(1, 0);
+        start_game(&mut deps, None, HEIGHT);
+        play_round(&mut deps, HEIGHT + 1, Play::WrongBag, Play::WrongBag);
+
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.result, Some(GameResult::BothLose.u8_val()));
+        assert_eq!(balance(&deps, "alice"), 0);
+        assert_eq!(balance(&deps, "bob"), 0);
+        assert_eq!(pool(&deps), 2 * LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+        assert_eq!(stats(&deps, "alice").both_lose, 1);
+        assert_eq!(stats(&deps, "bob").both_lose, 1);
+    }
+
+    #[test]
+    fn test_withdraw_before_opponent_joins() {
+        let mut deps = setup(1, 0);
+        join(&mut deps, "alice", None, LOW, HEIGHT).unwrap();
+        handle_at(&mut deps, "alice", 0, HEIGHT + 1, HandleMsg::Withdraw { v2: None, padding: None }).unwrap();
+        assert!(game(&deps, "alice").finished);
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+        assert!(get_lobby(&deps.storage, StakesTier::Low.u8_val(), DENOM).is_empty());
+        assert_eq!(stats(&deps, "alice").withdrawals, 1);
+
+        join(&mut deps, "bob", None, LOW, HEIGHT + 2).unwrap();
+        assert_eq!(game(&deps, "bob").round, 0);
+        join(&mut deps, "alice", None, LOW, HEIGHT + 2).unwrap();
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT + 3, HandleMsg::Withdraw { v2: None, padding: None })), 4018);
+    }
+
+    #[test]
+    fn test_insurance_powerup_refunds_losing_wager() {
+        let mut deps = setup(1, 0);
+        let game_idx = start_game(&mut deps, None, HEIGHT);
+        deps.querier.description = Some("insurance".to_string());
+        let msg = HandleMsg::BatchReceiveNft {
+            sender: HumanAddr::from("alice"),
+            from: HumanAddr::from("alice"),
+            token_ids: vec!["badge".to_string()],
+            msg: None,
+        };
+        let res = handle_at(&mut deps, "minter", 0, HEIGHT, msg.clone()).unwrap();
+        assert_eq!(res.log[0], log("action", "batch_receive_nft"));
+        assert!(res.log.contains(&log("game_idx", game_idx)));
+        let game_state = game(&deps, "alice");
+        assert_eq!(game_state.player_a_powerup, Some(POWERUP_INSURANCE));
+        assert_eq!(game_state.player_a_powerup_token_id, Some("badge".to_string()));
+        assert_eq!(error_code(handle_at(&mut deps, "minter", 0, HEIGHT, msg)), 5003);
+
+        play_round(&mut deps, HEIGHT + 1, Play::Abstain, Play::Opponent);
+        let game_state = game(&deps, "alice");
+        assert_eq!(game_state.result, Some(GameResult::BWon.u8_val()));
+        assert!(game_state.player_a_powerup_applied);
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(balance(&deps, "bob"), LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+    }
+
+    #[test]
+    fn test_balance_deposit_and_claim() {
+        let mut deps = setup(1, 0);
+        let claim = |amount: Option<u128>| HandleMsg::Claim { amount: amount.map(Uint128), denom: None, padding: None };
+        let join_from_balance = HandleMsg::Join {
+            stakes: None,
+            entropy: None,
+            entropy_commitment: None,
+            from_balance: Some(true),
+            denom: None,
+            referrer: None,
+            v2: None,
+            padding: None,
+        };
+
+        handle_at(&mut deps, "alice", 3 * LOW, HEIGHT, HandleMsg::Deposit { padding: None }).unwrap();
+        assert_eq!(balance(&deps, "alice"), 3 * LOW);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", LOW, HEIGHT, join_from_balance.clone())), 2004);
+        assert_eq!(error_code(handle_at(&mut deps, "bob", 0, HEIGHT, join_from_balance.clone())), 2005);
+        handle_at(&mut deps, "alice", 0, HEIGHT, join_from_balance).unwrap();
+        assert_eq!(balance(&deps, "alice"), 2 * LOW);
+        assert_eq!(get_escrow(&deps.storage, DENOM), LOW);
+
+        let res = handle_at(&mut deps, "alice", 0, HEIGHT, claim(Some(500000))).unwrap();
+        match &res.messages[..] {
+            [CosmosMsg::Bank(BankMsg::Send { to_address, amount, .. })] => {
+                assert_eq!(to_address, &HumanAddr::from("alice"));
+                assert_eq!(amount, &coins(500000, DENOM));
+            },
+            _ => panic!("expected a bank send"),
+        }
+        assert_eq!(balance(&deps, "alice"), 1500000);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT, claim(Some(2 * LOW)))), 2009);
+
+        handle_at(&mut deps, "alice", 0, HEIGHT, claim(None)).unwrap();
+        assert_eq!(balance(&deps, "alice"), 0);
+        assert_eq!(error_code(handle_at(&mut deps, "alice", 0, HEIGHT, claim(None))), 2008);
+    }
+
+    #[test]
+    fn test_winner_paid_pot_less_rake() {
+        let mut deps = setup(1, 0);
+        handle_at(&mut deps, "admin", 0, HEIGHT, HandleMsg::SetRake { rake_bps: 1000, padding: None }).unwrap();
+        let msg = HandleMsg::SetFeeRouting {
+            pool_bps: 5000,
+            treasury_bps: 3000,
+            referral_bps: 2000,
+            treasury: Some(HumanAddr::from("treasury")),
+            padding: None,
+        };
+        handle_at(&mut deps, "admin", 0, HEIGHT, msg).unwrap();
+
+        handle_at(&mut deps, "alice", LOW, HEIGHT, join_msg(None, Some("carol"))).unwrap();
+        handle_at(&mut deps, "bob", LOW, HEIGHT, join_msg(None, Some("dave"))).unwrap();
+        play_round(&mut deps, HEIGHT + 1, Play::Opponent, Play::Abstain);
+
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.result, Some(GameResult::AWon.u8_val()));
+        assert_eq!(game_state.player_a_payout, Some(1800000));
+        assert_eq!(game_state.player_b_payout, Some(0));
+        assert_eq!(game_state.fees.len(), 1);
+        assert_eq!(game_state.fees[0].amount, 200000);
+
+        assert_eq!(balance(&deps, "alice"), 1800000);
+        assert_eq!(balance(&deps, "bob"), 0);
+        assert_eq!(balance(&deps, "treasury"), 60000);
+        assert_eq!(balance(&deps, "carol"), 40000);
+        assert_eq!(balance(&deps, "dave"), 0);
+        assert_eq!(pool(&deps), 100000);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+
+        assert_eq!(stats(&deps, "alice").wins, 1);
+        assert_eq!(stats(&deps, "alice").total_payout, 1800000);
+        assert_eq!(stats(&deps, "bob").losses, 1);
+    }
+
+    #[test]
+    fn test_rake_without_referrer_or_treasury() {
+        let mut deps = setup(1, 0);
+        handle_at(&mut deps, "admin", 0, HEIGHT, HandleMsg::SetRake { rake_bps: 1000, padding: None }).unwrap();
+        let msg = HandleMsg::SetFeeRouting {
+            pool_bps: 5000,
+            treasury_bps: 3000,
+            referral_bps: 2000,
+            treasury: Some(HumanAddr::from("treasury")),
+            padding: None,
+        };
+        handle_at(&mut deps, "admin", 0, HEIGHT, msg).unwrap();
+
+        start_game(&mut deps, None, HEIGHT);
+        play_round(&mut deps, HEIGHT + 1, Play::Abstain, Play::Opponent);
+        assert_eq!(balance(&deps, "bob"), 1800000);
+        assert_eq!(balance(&deps, "treasury"), 100000);
+        assert_eq!(pool(&deps), 100000);
+
+        let msg = HandleMsg::SetFeeRouting { pool_bps: 5000, treasury_bps: 3000, referral_bps: 2000, treasury: None, padding: None };
+        handle_at(&mut deps, "admin", 0, HEIGHT + 2, msg).unwrap();
+        start_game(&mut deps, None, HEIGHT + 2);
+        play_round(&mut deps, HEIGHT + 3, Play::Abstain, Play::Opponent);
+        assert_eq!(balance(&deps, "bob"), 3600000);
+        assert_eq!(balance(&deps, "treasury"), 100000);
+        assert_eq!(pool(&deps), 300000);
+    }
+
+    #[test]
+    fn test_rake_fixed_when_game_created() {
+        let mut deps = setup(1, 0);
+        start_game(&mut deps, None, HEIGHT);
+        handle_at(&mut deps, "admin", 0, HEIGHT, HandleMsg::SetRake { rake_bps: 1000, padding: None }).unwrap();
+        play_round(&mut deps, HEIGHT + 1, Play::Opponent, Play::Abstain);
+        assert_eq!(balance(&deps, "alice"), 2 * LOW);
+        assert!(game(&deps, "alice").fees.is_empty());
+        assert_eq!(pool(&deps), 0);
+    }
+
+    #[test]
+    fn test_fee_routing_fixed_when_game_created() {
+        let mut deps = setup(1, 10000000);
+        handle_at(&mut deps, "admin", 0, HEIGHT, HandleMsg::SetRake { rake_bps: 1000, padding: None }).unwrap();
+        let msg = HandleMsg::SetFeeRouting {
+            pool_bps: 5000,
+            treasury_bps: 3000,
+            referral_bps: 2000,
+            treasury: Some(HumanAddr::from("treasury")),
+            padding: None,
+        };
+        handle_at(&mut deps, "admin", 0, HEIGHT, msg).unwrap();
+        let game_idx = start_game(&mut deps, None, HEIGHT);
+
+        let msg = HandleMsg::SetFeeRouting { pool_bps: 10000, treasury_bps: 0, referral_bps: 0, treasury: None, padding: None };
+        handle_at(&mut deps, "admin", 0, HEIGHT + 1, msg).unwrap();
+        handle_at(&mut deps, "admin", 0, HEIGHT + 1, HandleMsg::SetRake { rake_bps: 0, padding: None }).unwrap();
+        match from_binary(&query_jackpot(&deps, None, Some(game_idx)).unwrap()).unwrap() {
+            QueryAnswer::Jackpot { amount, after_rake, .. } => {
+                assert_eq!(amount, Uint128(5000000));
+                assert_eq!(after_rake, Uint128(4500000));
+            },
+            _ => panic!("expected the jackpot"),
+        }
+        match from_binary(&query_jackpot(&deps, None, None).unwrap()).unwrap() {
+            QueryAnswer::Jackpot { after_rake, .. } => assert_eq!(after_rake, Uint128(5000000)),
+            _ => panic!("expected the jackpot"),
+        }
+
+        play_round(&mut deps, HEIGHT + 2, Play::Abstain, Play::Opponent);
+        assert_eq!(balance(&deps, "bob"), 1800000);
+        assert_eq!(balance(&deps, "treasury"), 100000);
+        assert_eq!(pool(&deps), 10100000);
+    }
+
+    #[test]
+    fn test_jackpot_rules() {
+        assert_eq!(StoredJackpotRule::Fraction { bps: 5000 }.jackpot(1000), 500);
+        assert_eq!(StoredJackpotRule::Fraction { bps: 10000 }.jackpot(1000), 1000);
+
+        let capped = StoredJackpotRule::CappedFraction { bps: 5000, cap: 300 };
+        assert_eq!(capped.jackpot(1000), 300);
+        assert_eq!(capped.jackpot(400), 200);
+
+        let reserve = StoredJackpotRule::Reserve { bps: 5000, min_reserve: 800 };
+        assert_eq!(reserve.jackpot(1000), 200);
+        assert_eq!(reserve.jackpot(4000), 2000);
+        assert_eq!(reserve.jackpot(500), 0);
+
+        let tiers = StoredJackpotRule::Tiers { tiers: vec![(100, 1000), (1000, 5000)] };
+        assert_eq!(tiers.jackpot(50), 0);
+        assert_eq!(tiers.jackpot(500), 50);
+        assert_eq!(tiers.jackpot(2000), 1000);
+
+        assert_eq!(JackpotRule::Fraction { bps: 10001 }.to_stored().unwrap_err().code(), 1017);
+        assert_eq!(JackpotRule::Tiers { tiers: vec![] }.to_stored().unwrap_err().code(), 1018);
+        let unordered = JackpotRule::Tiers { tiers: vec![
+            JackpotTier { min_pool: Uint128(1000), bps: 5000 },
+            JackpotTier { min_pool: Uint128(100), bps: 1000 },
+        ] };
+        assert_eq!(unordered.to_stored().unwrap_err().code(), 1018);
+        let too_high = JackpotRule::Tiers { tiers: vec![JackpotTier { min_pool: Uint128(100), bps: 10001 }] };
+        assert_eq!(too_high.to_stored().unwrap_err().code(), 1017);
+    }
+
+    #[test]
+    fn test_reward_round_pays_jackpot_and_mints_nft() {
+        let mut deps = setup(1, 10000000);
+        start_game(&mut deps, None, HEIGHT);
+        play_round(&mut deps, HEIGHT + 1, Play::Abstain, Play::Abstain);
+
+        let game_state = game(&deps, "alice");
+        assert!(!game_state.finished);
+        assert!(game_state.in_reward_round());
+
+        let res = pick_reward(&mut deps, "alice", "jackpot", HEIGHT + 2).unwrap();
+        assert!(res.messages.is_empty());
+        let res = pick_reward(&mut deps, "bob", "nft", HEIGHT + 2).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let game_state = game(&deps, "alice");
+        assert!(game_state.finished);
+        assert_eq!(game_state.result, Some(GameResult::AJackpotBNft.u8_val()));
+        assert_eq!(game_state.jackpot_reward, Some(5000000));
+        assert_eq!(game_state.nft_token_id, Some(format!("game-badge-{}", current_game(&deps, "alice"))));
+        assert_eq!(balance(&deps, "alice"), 5000000);
+        assert_eq!(balance(&deps, "bob"), 0);
+        assert_eq!(pool(&deps), 5000000);
+        assert_eq!(get_escrow(&deps.storage, DENOM), 0);
+        assert_eq!(stats(&deps, "alice").jackpots_won, 1);
+        assert_eq!(stats(&deps, "bob").nfts_won, 1);
+    }
+
+    #[test]
+    fn test_same_reward_pick_refunds_wagers() {
+        let mut deps = setup(1, 10000000);
+        start_game(&mut deps, None, HEIGHT);
+        play_round(&mut deps, HEIGHT + 1, Play::Opponent, Play::Opponent);
+        assert_eq!(error_code(pick_reward(&mut deps, "alice", "badge", HEIGHT + 2)), 4017);
+
+        pick_reward(&mut deps, "alice", "nft", HEIGHT + 2).unwrap();
+        let res = pick_reward(&mut deps, "bob", "nft", HEIGHT + 2).unwrap();
+        assert!(res.messages.is_empty());
+
+        let game_state = game(&deps, "alice");
+        assert_eq!(game_state.result, Some(GameResult::NoReward.u8_val()));
+        assert_eq!(balance(&deps, "alice"), LOW);
+        assert_eq!(balance(&deps, "bob"), LOW);
+        assert_eq!(pool(&deps), 10000000);
+        assert_eq!(stats(&deps, "alice").no_rewards, 1);
+    }
+
+    #[test]
+    fn test_pool_withdrawal_timelock() {
+        let mut deps = setup(1, 10000000);
+        let propose = |amount: u128| HandleMsg::ProposePoolWithdrawal {
+            amount: Uint128(amount),
+            denom: None,
+            recipient: None,
+            padding: None,
+        };
+        let execute = || HandleMsg::ExecutePoolWithdrawal { denom: None, padding: None };
+
+        assert_eq!(error_code(handle_at(&mut deps, "bob", 0, HEIGHT, propose(4000000))), 1001);
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, HEIGHT, execute())), 6003);
+        handle_at(&mut deps, "admin", 0, HEIGHT, propose(4000000)).unwrap();
+        let pending = get_pending_withdrawal(&deps.storage, DENOM).unwrap();
+        assert_eq!(pending.executable_block, HEIGHT + DEFAULT_POOL_WITHDRAWAL_DELAY);
+
+        let unlocked = HEIGHT + DEFAULT_POOL_WITHDRAWAL_DELAY;
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, unlocked - 1, execute())), 6004);
+        let res = handle_at(&mut deps, "admin", 0, unlocked, execute()).unwrap();
+        match &res.messages[..] {
+            [CosmosMsg::Bank(BankMsg::Send { to_address, amount, .. })] => {
+                assert_eq!(to_address, &HumanAddr::from("admin"));
+                assert_eq!(amount, &coins(4000000, DENOM));
+            },
+            _ => panic!("expected a bank send"),
+        }
+        assert_eq!(pool(&deps), 6000000);
+        assert!(get_pending_withdrawal(&deps.storage, DENOM).is_none());
+        assert_eq!(error_code(handle_at(&mut deps, "admin", 0, unlocked, execute())), 6003);
+
+        handle_at(&mut deps, "admin", 0, unlocked, propose(7000000)).unwrap();
+        let res = handle_at(&mut deps, "admin", 0, unlocked + DEFAULT_POOL_WITHDRAWAL_DELAY, execute());
+        assert_eq!(error_code(res), 6006);
+        assert_eq!(pool(&deps), 6000000);
+    }
+
+    #[test]
+    fn test_game_state_hides_opponent_moves() {
+        let mut deps = setup(1, 0);
+        start_game(&mut deps, Some("practice"), HEIGHT);
+        let round_state = game(&deps, "alice").round_state.unwrap();
+        let a_chip = round_state.player_a_chip.to_humanized().unwrap();
+        let b_chip = round_state.player_b_chip.to_humanized().unwrap();
+        let a_first = Hint::i_have_from_color(a_chip.color.clone());
+        let b_first = Hint::i_have_from_color(b_chip.color.clone());
+
+        submit(&mut deps, "alice", a_first.clone(), HEIGHT + 1).unwrap();
+        let state = state_v2(&deps, "bob");
+        assert_eq!(state.opponent_first_submit, None);
+        assert!(state.reveal.is_none());
+        assert_eq!(state_v2(&deps, "alice").first_submit, Some(a_first.clone()));
+
+        submit(&mut deps, "bob", b_first.clone(), HEIGHT + 2).unwrap();
+        assert_eq!(state_v2(&deps, "bob").opponent_first_submit, Some(a_first));
+        assert_eq!(state_v2(&deps, "alice").opponent_first_submit, Some(b_first));
+
+        // nobody has bob's color is provably false, so bob gets an extra secret instead
+        let lie = match b_chip.color {
+            Color::Red => Hint::NobodyHasRed,
+            Color::Green => Hint::NobodyHasGreen,
+            Color::Blue => Hint::NobodyHasBlue,
+            Color::Black => Hint::NobodyHasBlack,
+        };
+        submit(&mut deps, "alice", lie, HEIGHT + 3).unwrap();
+        let b_second = Hint::from_u8(round_state.player_b_first_hint).unwrap();
+        submit(&mut deps, "bob", b_second.clone(), HEIGHT + 4).unwrap();
+        let state = state_v2(&deps, "bob");
+        assert_eq!(state.opponent_second_submit, None);
+        assert!(state.second_extra_secret.is_some());
+        let state = state_v2(&deps, "alice");
+        assert_eq!(state.opponent_second_submit, Some(b_second));
+        assert_eq!(state.second_extra_secret, None);
+
+        let abstain = Play::Abstain.guess(&a_chip, &b_chip);
+        guess(&mut deps, "alice", abstain.clone(), HEIGHT + 5).unwrap();
+        assert_eq!(state_v2(&deps, "alice").opponent_guess, None);
+        assert_eq!(state_v2(&deps, "bob").opponent_guess, None);
+        assert!(state_v2(&deps, "alice").reveal.is_none());
+
+        guess(&mut deps, "bob", abstain.clone(), HEIGHT + 6).unwrap();
+        let state = state_v2(&deps, "alice");
+        assert_eq!(state.finished, Some(true));
+        assert_eq!(state.opponent_guess, Some(abstain));
+        assert_eq!(state.reveal.map(|reveal| reveal.len()), Some(1));
+    }
+
+    #[test]
+    fn test_transcript_hides_opponent_moves() {
+        let mut deps = setup(1, 0);
+        let game_idx = start_game(&mut deps, Some("practice"), HEIGHT);
+        let round_state = game(&deps, "alice").round_state.unwrap();
+        let a_chip = round_state.player_a_chip.to_humanized().unwrap();
+        let b_chip = round_state.player_b_chip.to_humanized().unwrap();
+        let a_first = Hint::i_have_from_color(a_chip.color.clone());
+        submit(&mut deps, "alice", a_first.clone(), HEIGHT + 1).unwrap();
+
+        let (finished, events) = transcript(&deps, "bob", game_idx);
+        assert!(!finished);
+        assert_eq!(events[0], TranscriptEvent::Join { player: TranscriptPlayer::Opponent, block: HEIGHT, wager: Uint128(0) });
+        assert_eq!(events[1], TranscriptEvent::Join { player: TranscriptPlayer::You, block: HEIGHT, wager: Uint128(0) });
+        assert_eq!(submitted_hints(&events), vec![(TranscriptPlayer::Opponent, None)]);
+        let (_, events) = transcript(&deps, "alice", game_idx);
+        assert_eq!(submitted_hints(&events), vec![(TranscriptPlayer::You, Some(a_first.clone()))]);
+        assert_eq!(query_game_transcript(&deps, &HumanAddr::from("carol"), game_idx).unwrap_err().code(), 4022);
+
+        let b_first = Hint::i_have_from_color(b_chip.color.clone());
+        submit(&mut deps, "bob", b_first.clone(), HEIGHT + 2).unwrap();
+        let (_, events) = transcript(&deps, "bob", game_idx);
+        assert_eq!(submitted_hints(&events), vec![
+            (TranscriptPlayer::Opponent, None),
+            (TranscriptPlayer::You, Some(b_first.clone())),
+        ]);
+
+        let a_second = Hint::from_u8(round_state.player_a_first_hint).unwrap();
+        let b_second = Hint::from_u8(round_state.player_b_first_hint).unwrap();
+        submit(&mut deps, "alice", a_second.clone(), HEIGHT + 3).unwrap();
+        submit(&mut deps, "bob", b_second.clone(), HEIGHT + 4).unwrap();
+        guess(&mut deps, "alice", Play::Abstain.guess(&a_chip, &b_chip), HEIGHT + 5).unwrap();
+        guess(&mut deps, "bob", Play::Abstain.guess(&b_chip, &a_chip), HEIGHT + 6).unwrap();
+
+        let (finished, events) = transcript(&deps, "bob", game_idx);
+        assert!(finished);
+        assert_eq!(submitted_hints(&events), vec![
+            (TranscriptPlayer::Opponent, Some(a_first)),
+            (TranscriptPlayer::You, Some(b_first)),
+            (TranscriptPlayer::Opponent, Some(a_second)),
+            (TranscriptPlayer::You, Some(b_second)),
+        ]);
+        let payouts = events.iter().filter(|event| matches!(event, TranscriptEvent::Payout { .. })).count();
+        assert_eq!(payouts, 2);
+    }
+
+    #[test]
+    fn test_v2_messages_and_answers() {
+        let msg: HandleMsg = from_slice(br#"{"submit_v2":{"hint":"nobody_has_red"}}"#).unwrap();
+        assert_eq!(msg, HandleMsg::SubmitV2 { hint: Hint::NobodyHasRed, padding: None });
+        let msg: HandleMsg = from_slice(br#"{"guess_v2":{"guess":{"target":"opponent","color":"green","shape":"star"}}}"#).unwrap();
+        assert_eq!(msg, HandleMsg::GuessV2 {
+            guess: Guess { target: Target::Opponent, color: Some(Color::Green), shape: Some(Shape::Star) },
+            padding: None,
+        });
+        let msg: HandleMsg = from_slice(br#"{"guess_v2":{"guess":{"target":"abstain"}}}"#).unwrap();
+        assert_eq!(msg, HandleMsg::GuessV2 { guess: Guess { target: Target::Abstain, color: None, shape: None }, padding: None });
+        assert!(from_slice::<HandleMsg>(br#"{"submit_v2":{"hint":"i_have_purple"}}"#).is_err());
+
+        let mut deps = setup(1, 0);
+        let invalid = Guess { target: Target::Abstain, color: Some(Color::Red), shape: None };
+        assert_eq!(error_code(guess(&mut deps, "alice", invalid, HEIGHT)), 4015);
+        let invalid = Guess { target: Target::Bag, color: Some(Color::Red), shape: None };
+        assert_eq!(error_code(guess(&mut deps, "alice", invalid, HEIGHT)), 4015);
+
+        let msg = HandleMsg::Join { stakes: None, entropy: None, entropy_commitment: None, from_balance: None, denom: None, referrer: None, v2: Some(true), padding: None };
+        let res = handle_at(&mut deps, "alice", LOW, HEIGHT, msg).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::Join { game_state: None, game_state_v2: Some(state), .. } => {
+                assert_eq!(state.stakes, Some("low".to_string()));
+                assert_eq!(state.round, Some(0));
+                assert_eq!(state.wager, Some(Uint128(LOW)));
+            },
+            _ => panic!("expected the v2 game state"),
+        }
+        let res = join(&mut deps, "bob", None, LOW, HEIGHT).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::Join { game_state: Some(_), game_state_v2: None, .. } => {},
+            _ => panic!("expected the v1 game state"),
+        }
+    }
+
+    #[test]
+    fn test_contract_error_codes() {
+        match StdError::from(ContractError::WithdrawalLocked { executable_block: 100 }) {
+            StdError::GenericErr { msg, .. } => assert!(msg.starts_with("{\"code\":6004,")),
+            error => panic!("unexpected error {:?}", error),
+        }
+        match StdError::from(ContractError::Std { source: StdError::not_found("game") }) {
+            StdError::NotFound { .. } => {},
+            error => panic!("unexpected error {:?}", error),
+        }
+        let config = get_config(&setup(1, 0).storage).unwrap();
+        assert_eq!(config.stakes_for_tier("uatom", &StakesTier::Low).unwrap_err().code(), 2001);
+        assert_eq!(config.stakes_for_tier(DENOM, &StakesTier::Practice).unwrap(), 0);
+        assert_eq!(config.stakes_for_tier(DENOM, &StakesTier::High).unwrap(), DEFAULT_HIGH_STAKES.u128());
     }
 }
-*/
\ No newline at end of file
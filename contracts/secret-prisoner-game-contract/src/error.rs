@@ -0,0 +1,472 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use snafu::Snafu;
+use cosmwasm_std::{to_vec, StdError, Uint128};
+
+// Errors returned by the game contract. Each error has a stable numeric code (see `code`) and its
+// fields as structured context. At the entry points a contract error becomes a generic StdError
+// whose message is a json ContractErrorResponse, errors from storage, the api or serialization
+// are passed through unchanged.
+//
+// Codes are grouped by what failed, and are never reused or renumbered:
+//   1xxx admin, permissions and configuration
+//   2xxx funds and balances
+//   3xxx creating and joining games
+//   4xxx playing a game
+//   5xxx powerup nfts
+//   6xxx jackpot pool
+//
+// The doc comment of each variant starts with its code so the code shows up in the schema.
+#[derive(Snafu, Debug, Serialize, JsonSchema)]
+#[snafu(visibility(pub))]
+#[serde(rename_all = "snake_case")]
+pub enum ContractError {
+    #[snafu(display("{}", source))]
+    #[serde(skip)]
+    Std { source: StdError },
+
+    /// 1001
+    #[snafu(display("This is an admin command and can only be run from the admin address"))]
+    AdminOnly {},
+
+    /// 1002
+    #[snafu(display("This is an admin query and can only be run with a permit from the admin address"))]
+    AdminQueryOnly {},
+
+    /// 1003
+    #[snafu(display("The contract admin has temporarily disabled this action"))]
+    ActionDisabled {},
+
+    /// 1004
+    #[snafu(display("No permission to query {}, got permissions {}", query, permissions))]
+    NoPermission { query: String, permissions: String },
+
+    /// 1005
+    #[snafu(display("Color and shape weights must be greater than 0"))]
+    InvalidWeights {},
+
+    /// 1006
+    #[snafu(display("Denoms must be non-empty and different from each other"))]
+    InvalidDenoms {},
+
+    /// 1007
+    #[snafu(display("Stakes for {} must be non-zero and ordered low <= medium <= high", denom))]
+    InvalidDenomStakes { denom: String },
+
+    /// 1008
+    #[snafu(display("Timeout must be at least one block"))]
+    InvalidTimeout {},
+
+    /// 1009
+    #[snafu(display("Rounds per game must be between 1 and {}", max))]
+    InvalidRoundsPerGame { max: u8 },
+
+    /// 1010
+    #[snafu(display("Lobby expiration must be at least one block"))]
+    InvalidLobbyExpiration {},
+
+    /// 1011
+    #[snafu(display("Rake cannot be more than {} basis points", max_bps))]
+    RakeTooHigh { max_bps: u16 },
+
+    /// 1012
+    #[snafu(display("Pool, treasury and referral shares must add up to 10000 basis points"))]
+    InvalidFeeRouting {},
+
+    /// 1013
+    #[snafu(display("Invalid lobby policy, must be one of fifo or random"))]
+    InvalidLobbyPolicy {},
+
+    /// 1014
+    #[snafu(display("Invalid sort, must be one of wins, win_rate or net_winnings"))]
+    InvalidSort {},
+
+    /// 1015
+    #[snafu(display("Pool withdrawal delay must be between {} and {} blocks", min, max))]
+    InvalidPoolWithdrawalDelay { min: u64, max: u64 },
+
+    /// 1016
+    #[snafu(display("{} still has a pool, escrowed wagers, balances or open games and cannot be removed", denom))]
+    DenomInUse { denom: String },
+
+    /// 1017
+    #[snafu(display("Jackpot fraction cannot be more than 10000 basis points"))]
+    JackpotBpsTooHigh {},
+
+    /// 1018
+    #[snafu(display("Jackpot tiers must be ordered by increasing min_pool"))]
+    InvalidJackpotTiers {},
+
+    /// 2001
+    #[snafu(display("{} is not accepted for wagers", denom))]
+    DenomNotAccepted { denom: String },
+
+    /// 2002
+    #[snafu(display("Can only send funds in one denom"))]
+    MultipleDenoms {},
+
+    /// 2003
+    #[snafu(display("Funds were sent in {} instead of {}", sent, expected))]
+    WrongDenom { sent: String, expected: String },
+
+    /// 2004
+    #[snafu(display("Cannot send funds when joining from balance"))]
+    FundsWithBalance {},
+
+    /// 2005
+    #[snafu(display("Insufficient balance, must have {} {}", amount, denom))]
+    InsufficientBalance { amount: Uint128, denom: String },
+
+    /// 2006
+    #[snafu(display("Cannot send funds to join a practice game"))]
+    FundsForPractice {},
+
+    /// 2007
+    #[snafu(display("Incorrect amount sent, must be {} {}", amount, denom))]
+    IncorrectAmount { amount: Uint128, denom: String },
+
+    /// 2008
+    #[snafu(display("Nothing to claim"))]
+    NothingToClaim {},
+
+    /// 2009
+    #[snafu(display("Cannot claim more than your balance of {} {}", balance, denom))]
+    ClaimExceedsBalance { balance: Uint128, denom: String },
+
+    /// 2010
+    #[snafu(display("Nothing to deposit"))]
+    NothingToDeposit {},
+
+    /// 2011
+    #[snafu(display("No token is configured for wagers"))]
+    NoToken {},
+
+    /// 2012
+    #[snafu(display("Tokens were not sent from the configured token contract"))]
+    UnknownToken {},
+
+    /// 2013
+    #[snafu(display("Receive message is missing"))]
+    MissingReceiveMsg {},
+
+    /// 2014
+    #[snafu(display("Cannot seed jackpot pool with {}", denom))]
+    CannotSeedPool { denom: String },
+
+    /// 3001
+    #[snafu(display("You must finish current game before beginning a new one"))]
+    AlreadyInGame {},
+
+    /// 3002
+    #[snafu(display("Entropy commitment given without entropy"))]
+    CommitmentWithoutEntropy {},
+
+    /// 3003
+    #[snafu(display("Entropy cannot be empty"))]
+    EmptyEntropy {},
+
+    /// 3004
+    #[snafu(display("Entropy does not match entropy commitment"))]
+    EntropyMismatch {},
+
+    /// 3005
+    #[snafu(display("Invalid stakes, must be one of practice, low, medium, or high"))]
+    InvalidStakes {},
+
+    /// 3006
+    #[snafu(display("Private game must have an opponent or an invite code"))]
+    PrivateGameWithoutOpponent {},
+
+    /// 3007
+    #[snafu(display("You cannot challenge yourself"))]
+    ChallengeSelf {},
+
+    /// 3008
+    #[snafu(display("Invite code cannot be empty"))]
+    EmptyInviteCode {},
+
+    /// 3009
+    #[snafu(display("No private game to join"))]
+    NoPrivateGame {},
+
+    /// 3010
+    #[snafu(display("You are not invited to this game"))]
+    NotInvited {},
+
+    /// 3011
+    #[snafu(display("Invalid invite code"))]
+    InvalidInviteCode {},
+
+    /// 3012
+    #[snafu(display("The private game is played in {}", denom))]
+    PrivateGameDenom { denom: String },
+
+    /// 3013
+    #[snafu(display("Display name must be 1 to {} characters", max))]
+    InvalidDisplayName { max: u32 },
+
+    /// 3014
+    #[snafu(display("You cannot refer yourself"))]
+    ReferSelf {},
+
+    /// 4001
+    #[snafu(display("You cannot {} before joining a game", action))]
+    NotInGame { action: String },
+
+    /// 4002
+    #[snafu(display("You have not joined a game"))]
+    NoGame {},
+
+    /// 4003
+    #[snafu(display("Game is finished, join a new game"))]
+    GameFinished {},
+
+    /// 4004
+    #[snafu(display("First round has not been initialized"))]
+    RoundNotInitialized {},
+
+    /// 4005
+    #[snafu(display("Finished round with {}", moves))]
+    RoundsFinished { moves: String },
+
+    /// 4006
+    #[snafu(display("Cannot accept a submission from player"))]
+    MoveNotAccepted {},
+
+    /// 4007
+    #[snafu(display("Assertions must have different targets: i_have and nobody_has"))]
+    SameAssertionTarget {},
+
+    /// 4008
+    #[snafu(display("Second assertion cannot contradict first assertion"))]
+    ContradictingAssertion {},
+
+    /// 4009
+    #[snafu(display("Not a submission round"))]
+    NotSubmissionRound {},
+
+    /// 4010
+    #[snafu(display("Not a guess round"))]
+    NotGuessRound {},
+
+    /// 4011
+    #[snafu(display("Invalid hint"))]
+    InvalidHint {},
+
+    /// 4012
+    #[snafu(display("Hint must be either a color or shape but not both"))]
+    HintColorAndShape {},
+
+    /// 4013
+    #[snafu(display("Invalid color"))]
+    InvalidColor {},
+
+    /// 4014
+    #[snafu(display("Invalid shape"))]
+    InvalidShape {},
+
+    /// 4015
+    #[snafu(display("Invalid guess"))]
+    InvalidGuess {},
+
+    /// 4016
+    #[snafu(display("Reward round has not started"))]
+    RewardRoundNotStarted {},
+
+    /// 4017
+    #[snafu(display("Invalid reward selection"))]
+    InvalidReward {},
+
+    /// 4018
+    #[snafu(display("Cannot withdraw once another player has joined game"))]
+    OpponentJoined {},
+
+    /// 4019
+    #[snafu(display("Cannot force endgame until another player has joined game"))]
+    NoOpponent {},
+
+    /// 4020
+    #[snafu(display("Opponent has not timed out"))]
+    OpponentNotTimedOut {},
+
+    /// 4021
+    #[snafu(display("Invalid round error"))]
+    InvalidRound {},
+
+    /// 4022
+    #[snafu(display("You did not play in this game"))]
+    NotAPlayer {},
+
+    /// 4023
+    #[snafu(display("Refunds can only be claimed while the contract is stopped"))]
+    RefundsUnavailable {},
+
+    /// 4024
+    #[snafu(display("Game is finished, nothing to refund"))]
+    NothingToRefund {},
+
+    /// 5001
+    #[snafu(display("Invalid round for Insurance powerup"))]
+    InvalidPowerupRound {},
+
+    /// 5002
+    #[snafu(display("Can only send one powerup nft at a time"))]
+    MultiplePowerups {},
+
+    /// 5003
+    #[snafu(display("Can only apply one powerup nft per game"))]
+    PowerupApplied {},
+
+    /// 5004
+    #[snafu(display("You did not send a powerup nft"))]
+    NotPowerup {},
+
+    /// 5005
+    #[snafu(display("Invalid private metadata for powerup nft"))]
+    InvalidPowerupMetadata {},
+
+    /// 6001
+    #[snafu(display("No funds were sent to the pool"))]
+    NoPoolFunds {},
+
+    /// 6002
+    #[snafu(display("Withdrawal amount must be greater than 0"))]
+    ZeroWithdrawal {},
+
+    /// 6003
+    #[snafu(display("No pool withdrawal is pending for {}", denom))]
+    NoPendingWithdrawal { denom: String },
+
+    /// 6004
+    #[snafu(display("Pool withdrawal cannot be executed before block {}", executable_block))]
+    WithdrawalLocked { executable_block: u64 },
+
+    /// 6005
+    #[snafu(display("A pool withdrawal is pending for {}, execute it before removing the denom", denom))]
+    WithdrawalPending { denom: String },
+
+    /// 6006
+    #[snafu(display("Not enough funds in the {} pool, {} available", denom, pool))]
+    PoolTooSmall { denom: String, pool: Uint128 },
+}
+
+impl ContractError {
+    // stable code of the error, 0 for errors passed through from cosmwasm
+    pub fn code(&self) -> u16 {
+        match self {
+            ContractError::Std { .. } => 0,
+            ContractError::AdminOnly { .. } => 1001,
+            ContractError::AdminQueryOnly { .. } => 1002,
+            ContractError::ActionDisabled { .. } => 1003,
+            ContractError::NoPermission { .. } => 1004,
+            ContractError::InvalidWeights { .. } => 1005,
+            ContractError::InvalidDenoms { .. } => 1006,
+            ContractError::InvalidDenomStakes { .. } => 1007,
+            ContractError::InvalidTimeout { .. } => 1008,
+            ContractError::InvalidRoundsPerGame { .. } => 1009,
+            ContractError::InvalidLobbyExpiration { .. } => 1010,
+            ContractError::RakeTooHigh { .. } => 1011,
+            ContractError::InvalidFeeRouting { .. } => 1012,
+            ContractError::InvalidLobbyPolicy { .. } => 1013,
+            ContractError::InvalidSort { .. } => 1014,
+            ContractError::InvalidPoolWithdrawalDelay { .. } => 1015,
+            ContractError::DenomInUse { .. } => 1016,
+            ContractError::JackpotBpsTooHigh { .. } => 1017,
+            ContractError::InvalidJackpotTiers { .. } => 1018,
+            ContractError::DenomNotAccepted { .. } => 2001,
+            ContractError::MultipleDenoms { .. } => 2002,
+            ContractError::WrongDenom { .. } => 2003,
+            ContractError::FundsWithBalance { .. } => 2004,
+            ContractError::InsufficientBalance { .. } => 2005,
+            ContractError::FundsForPractice { .. } => 2006,
+            ContractError::IncorrectAmount { .. } => 2007,
+            ContractError::NothingToClaim { .. } => 2008,
+            ContractError::ClaimExceedsBalance { .. } => 2009,
+            ContractError::NothingToDeposit { .. } => 2010,
+            ContractError::NoToken { .. } => 2011,
+            ContractError::UnknownToken { .. } => 2012,
+            ContractError::MissingReceiveMsg { .. } => 2013,
+            ContractError::CannotSeedPool { .. } => 2014,
+            ContractError::AlreadyInGame { .. } => 3001,
+            ContractError::CommitmentWithoutEntropy { .. } => 3002,
+            ContractError::EmptyEntropy { .. } => 3003,
+            ContractError::EntropyMismatch { .. } => 3004,
+            ContractError::InvalidStakes { .. } => 3005,
+            ContractError::PrivateGameWithoutOpponent { .. } => 3006,
+            ContractError::ChallengeSelf { .. } => 3007,
+            ContractError::EmptyInviteCode { .. } => 3008,
+            ContractError::NoPrivateGame { .. } => 3009,
+            ContractError::NotInvited { .. } => 3010,
+            ContractError::InvalidInviteCode { .. } => 3011,
+            ContractError::PrivateGameDenom { .. } => 3012,
+            ContractError::InvalidDisplayName { .. } => 3013,
+            ContractError::ReferSelf { .. } => 3014,
+            ContractError::NotInGame { .. } => 4001,
+            ContractError::NoGame { .. } => 4002,
+            ContractError::GameFinished { .. } => 4003,
+            ContractError::RoundNotInitialized { .. } => 4004,
+            ContractError::RoundsFinished { .. } => 4005,
+            ContractError::MoveNotAccepted { .. } => 4006,
+            ContractError::SameAssertionTarget { .. } => 4007,
+            ContractError::ContradictingAssertion { .. } => 4008,
+            ContractError::NotSubmissionRound { .. } => 4009,
+            ContractError::NotGuessRound { .. } => 4010,
+            ContractError::InvalidHint { .. } => 4011,
+            ContractError::HintColorAndShape { .. } => 4012,
+            ContractError::InvalidColor { .. } => 4013,
+            ContractError::InvalidShape { .. } => 4014,
+            ContractError::InvalidGuess { .. } => 4015,
+            ContractError::RewardRoundNotStarted { .. } => 4016,
+            ContractError::InvalidReward { .. } => 4017,
+            ContractError::OpponentJoined { .. } => 4018,
+            ContractError::NoOpponent { .. } => 4019,
+            ContractError::OpponentNotTimedOut { .. } => 4020,
+            ContractError::InvalidRound { .. } => 4021,
+            ContractError::NotAPlayer { .. } => 4022,
+            ContractError::RefundsUnavailable { .. } => 4023,
+            ContractError::NothingToRefund { .. } => 4024,
+            ContractError::InvalidPowerupRound { .. } => 5001,
+            ContractError::MultiplePowerups { .. } => 5002,
+            ContractError::PowerupApplied { .. } => 5003,
+            ContractError::NotPowerup { .. } => 5004,
+            ContractError::InvalidPowerupMetadata { .. } => 5005,
+            ContractError::NoPoolFunds { .. } => 6001,
+            ContractError::ZeroWithdrawal { .. } => 6002,
+            ContractError::NoPendingWithdrawal { .. } => 6003,
+            ContractError::WithdrawalLocked { .. } => 6004,
+            ContractError::WithdrawalPending { .. } => 6005,
+            ContractError::PoolTooSmall { .. } => 6006,
+        }
+    }
+}
+
+// message of the generic StdError a contract error is returned as, e.g.
+// {"code":4020,"message":"Opponent has not timed out","error":{"opponent_not_timed_out":{}}}
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct ContractErrorResponse {
+    pub code: u16,
+    pub message: String,
+    pub error: ContractError,
+}
+
+impl From<StdError> for ContractError {
+    fn from(source: StdError) -> Self {
+        ContractError::Std { source }
+    }
+}
+
+impl From<ContractError> for StdError {
+    fn from(error: ContractError) -> Self {
+        match error {
+            ContractError::Std { source } => source,
+            error => {
+                let message = error.to_string();
+                let response = ContractErrorResponse { code: error.code(), message: message.clone(), error };
+                match to_vec(&response) {
+                    Ok(json) => StdError::generic_err(String::from_utf8_lossy(&json)),
+                    Err(_) => StdError::generic_err(message),
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 pub mod contract;
+pub mod error;
 pub mod msg;
 pub mod state;
-mod types;
+pub mod types;
 mod random;
 
 #[cfg(target_arch = "wasm32")]
@@ -1,11 +1,15 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{HumanAddr, Uint128,};
+use cosmwasm_std::{Binary, Coin, HumanAddr, StdError, StdResult, Uint128,};
 use secret_toolkit::permit::Permit;
+use crate::error::ContractError;
+use crate::state::StoredJackpotRule;
+use crate::types::{Chip, GameResult, Guess, Hint, RoundResult};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub admin: Option<HumanAddr>,
+    // number of assertion/guessing rounds played before the reward round (1 to 10)
     pub rounds_per_game: u8,
 
     // default even weights for colors and shapes (25,25,25,25)
@@ -18,18 +22,34 @@ pub struct InitMsg {
     pub circle_weight: Option<u16>,
     pub star_weight: Option<u16>,
 
-    // stakes for a game in uscrt (default = 1000000)
-    pub stakes: Option<Uint128>,
-    //pub low_stakes: Coin,
-    //pub medium_stakes: Coin,
-    //pub high_stakes: Coin,
+    // stakes for each tier of game in uscrt, or in the token if one is configured
+    // (defaults: low = 1000000, medium = 5000000, high = 10000000)
+    pub low_stakes: Option<Uint128>,
+    pub medium_stakes: Option<Uint128>,
+    pub high_stakes: Option<Uint128>,
+    // other native denoms accepted for wagers, each with its own stakes, jackpot pool and lobby
+    pub denoms: Option<Vec<DenomStakes>>,
 
     // timeout per turn, in # of blocks
     pub timeout: Option<u64>,
 
+    // one of {"fifo", "random"}, how a joining player is paired with open games (default = "fifo")
+    pub lobby_policy: Option<String>,
+    // # of blocks an open game stays in the lobby before it expires (default = 600)
+    pub lobby_expiration: Option<u64>,
+
     // contract info for the powerup nft minter
     pub minter: ContractInfo,
 
+    // snip-20 token used for wagers, jackpot and payouts instead of uscrt (cannot be changed later)
+    pub token: Option<ContractInfo>,
+
+    // how much of the pool a jackpot pays out (default = fraction of 5000 bps, half of the pool)
+    pub jackpot_rule: Option<JackpotRule>,
+
+    // # of blocks between proposing and executing a pool withdrawal (default = 14400, 600 to 432000, cannot be changed later)
+    pub pool_withdrawal_delay: Option<u64>,
+
     pub entropy: String,
 }
 
@@ -38,11 +58,50 @@ pub struct InitMsg {
 pub enum HandleMsg {
     // Join a new game
     Join {
-        // one of {"low", "medium", "high"} or None means no money bet
-        //stakes: Option<String>,
+        // one of {"practice", "low", "medium", "high"}, defaults to "low", "practice" means no money bet
+        stakes: Option<String>,
+        // optional entropy mixed into the deal, with optional hex sha256 commitment to check it against
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        // take the wager from the player's deposited balance instead of the sent funds
+        from_balance: Option<bool>,
+        // denom of the wager, defaults to the denom of the sent funds or uscrt
+        denom: Option<String>,
+        // address that referred the player, credited the referral share of the rake on their winnings
+        referrer: Option<HumanAddr>,
+        // answer with the typed v2 game state
+        v2: Option<bool>,
         padding: Option<String>, 
     },
 
+    // Create a private game that only the invited opponent and/or holder of the invite code can join
+    CreatePrivateGame {
+        // one of {"practice", "low", "medium", "high"}, defaults to "low", "practice" means no money bet
+        stakes: Option<String>,
+        opponent: Option<HumanAddr>,
+        invite_code: Option<String>,
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        from_balance: Option<bool>,
+        denom: Option<String>,
+        referrer: Option<HumanAddr>,
+        v2: Option<bool>,
+        padding: Option<String>,
+    },
+
+    // Join the private game created by host, sending the same wager as the host
+    JoinPrivateGame {
+        host: HumanAddr,
+        invite_code: Option<String>,
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        from_balance: Option<bool>,
+        denom: Option<String>,
+        referrer: Option<HumanAddr>,
+        v2: Option<bool>,
+        padding: Option<String>,
+    },
+
     // Submit an assertion for the opponent
     Submit {
         // one of {"nobody_has", "bag_not"}
@@ -65,20 +124,41 @@ pub enum HandleMsg {
         padding: Option<String>,
     },
 
+    // Submit with a typed hint, e.g. "nobody_has_red" or "i_have_star"
+    SubmitV2 {
+        hint: Hint,
+        padding: Option<String>,
+    },
+
+    // Guess with a typed target, color and shape, color and shape are None if abstaining
+    GuessV2 {
+        guess: Guess,
+        padding: Option<String>,
+    },
+
     // Pick a reward if round 3 has been entered
     PickReward {
         // one of {"nft", "jackpot"}
         reward: String,
+        v2: Option<bool>,
         padding: Option<String>,
     },
 
     // Withdraw from a game if no opponent has joined
     Withdraw {
+        v2: Option<bool>,
         padding: Option<String>,
     },
 
     // Check if opponent has timed out and force endgame, if so
     ForceEndgame {
+        v2: Option<bool>,
+        padding: Option<String>,
+    },
+
+    // Refund wagers escrowed in current game to both players, only while contract status is StopAll
+    ClaimRefund {
+        v2: Option<bool>,
         padding: Option<String>,
     },
 
@@ -89,11 +169,264 @@ pub enum HandleMsg {
         msg: Option<String>,
     },
 
+    // snip-20 Send callback when a token is configured, msg is a ReceiveMsg
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+
     // Permit
     RevokePermit {
         permit_name: String,
         padding: Option<String>,
     },
+
+    // withdraws from the player's balance, which holds deposits, refunds and winnings. sends
+    // amount out, or the whole balance if amount is not given
+    Claim {
+        amount: Option<Uint128>,
+        // defaults to uscrt, or the token if one is configured
+        denom: Option<String>,
+        padding: Option<String>,
+    },
+
+    // adds the sent funds in an accepted denom, or the token sent through Receive, to the
+    // player's balance, so games can be joined with from_balance
+    Deposit {
+        padding: Option<String>,
+    },
+
+    // adds the sent funds to the jackpot pool of their denom, anyone can fund the pool
+    FundPool {
+        padding: Option<String>,
+    },
+
+    // show or hide player's stats on the public leaderboard
+    SetLeaderboardOptIn {
+        opt_in: bool,
+        display_name: Option<String>,
+        padding: Option<String>,
+    },
+
+    // Admin, changes only apply to games created afterwards
+    UpdateConfig {
+        config: ConfigUpdate,
+        padding: Option<String>,
+    },
+
+    ChangeAdmin {
+        address: HumanAddr,
+        padding: Option<String>,
+    },
+
+    SetMinter {
+        minter: ContractInfo,
+        padding: Option<String>,
+    },
+
+    // rake on winning payouts in basis points, only applies to games created afterwards
+    SetRake {
+        rake_bps: u16,
+        padding: Option<String>,
+    },
+
+    // how each rake is split in basis points, the shares must add up to 10000. the referral
+    // share goes to the referrer the winning player gave when joining the game
+    SetFeeRouting {
+        pool_bps: u16,
+        treasury_bps: u16,
+        referral_bps: u16,
+        treasury: Option<HumanAddr>,
+        padding: Option<String>,
+    },
+
+    SetJackpotRule {
+        rule: JackpotRule,
+        padding: Option<String>,
+    },
+
+    // withdraw from the pool once pool_withdrawal_delay blocks have passed, recipient defaults
+    // to the admin. Replaces any pending withdrawal in the denom and restarts the delay
+    ProposePoolWithdrawal {
+        amount: Uint128,
+        denom: Option<String>,
+        recipient: Option<HumanAddr>,
+        padding: Option<String>,
+    },
+
+    ExecutePoolWithdrawal {
+        denom: Option<String>,
+        padding: Option<String>,
+    },
+
+    // StopTransactions blocks new games while games in progress can finish,
+    // StopAll freezes all games and lets players claim refunds of their wagers
+    SetContractStatus {
+        level: ContractStatus,
+        padding: Option<String>,
+    },
+}
+
+// actions taken with snip-20 tokens sent to the contract, the amount sent is the wager or deposit
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    Join {
+        stakes: Option<String>,
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        referrer: Option<HumanAddr>,
+        v2: Option<bool>,
+    },
+
+    CreatePrivateGame {
+        stakes: Option<String>,
+        opponent: Option<HumanAddr>,
+        invite_code: Option<String>,
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        referrer: Option<HumanAddr>,
+        v2: Option<bool>,
+    },
+
+    JoinPrivateGame {
+        host: HumanAddr,
+        invite_code: Option<String>,
+        entropy: Option<String>,
+        entropy_commitment: Option<String>,
+        referrer: Option<HumanAddr>,
+        v2: Option<bool>,
+    },
+
+    Deposit {},
+
+    FundPool {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct FeeResponse {
+    pub amount: Uint128,
+    pub to_pool: Uint128,
+    pub to_treasury: Uint128,
+    pub to_referral: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct PoolChangeResponse {
+    pub denom: String,
+    pub added: Uint128,
+    pub removed: Uint128,
+    // pool size after the change
+    pub pool: Uint128,
+    // one of seed, both_lose, timeout, jackpot, rake, funding or withdrawal
+    pub reason: String,
+    pub game_idx: Option<u32>,
+    pub block: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct PendingWithdrawalResponse {
+    pub denom: String,
+    pub amount: Uint128,
+    pub recipient: HumanAddr,
+    pub proposed_block: u64,
+    pub executable_block: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct SolvencyResponse {
+    pub denom: String,
+    pub pool: Uint128,
+    // wagers of games that are not finished
+    pub escrow: Uint128,
+    // refunds and winnings credited to players and not yet claimed
+    pub claimable: Uint128,
+    // pool + escrow + claimable
+    pub liabilities: Uint128,
+    // contract's balance as queried from the bank or the token contract
+    pub balance: Uint128,
+    // balance - liabilities, a signed integer string as it can be negative
+    pub difference: String,
+    pub solvent: bool,
+}
+
+// jackpot paid out of a pool, fractions are in basis points and amounts in the pool's denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JackpotRule {
+    // fraction of the pool
+    Fraction { bps: u16 },
+    // fraction of the pool, up to cap
+    CappedFraction { bps: u16, cap: Uint128 },
+    // fraction of the pool, always leaving at least min_reserve in the pool
+    Reserve { bps: u16, min_reserve: Uint128 },
+    // fraction of the highest tier the pool has reached, nothing below the lowest tier
+    Tiers { tiers: Vec<JackpotTier> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JackpotTier {
+    pub min_pool: Uint128,
+    pub bps: u16,
+}
+
+impl JackpotRule {
+    pub fn to_stored(&self) -> Result<StoredJackpotRule, ContractError> {
+        let check_bps = |bps: u16| if bps > 10000 {
+            Err(ContractError::JackpotBpsTooHigh {})
+        } else {
+            Ok(bps)
+        };
+        let rule = match self {
+            JackpotRule::Fraction { bps } => StoredJackpotRule::Fraction { bps: check_bps(*bps)? },
+            JackpotRule::CappedFraction { bps, cap } => StoredJackpotRule::CappedFraction { bps: check_bps(*bps)?, cap: cap.u128() },
+            JackpotRule::Reserve { bps, min_reserve } => StoredJackpotRule::Reserve { bps: check_bps(*bps)?, min_reserve: min_reserve.u128() },
+            JackpotRule::Tiers { tiers } => {
+                if tiers.is_empty() || tiers.windows(2).any(|pair| pair[0].min_pool >= pair[1].min_pool) {
+                    return Err(ContractError::InvalidJackpotTiers {});
+                }
+                let mut stored_tiers: Vec<(u128, u16)> = vec![];
+                for tier in tiers {
+                    stored_tiers.push((tier.min_pool.u128(), check_bps(tier.bps)?));
+                }
+                StoredJackpotRule::Tiers { tiers: stored_tiers }
+            },
+        };
+        Ok(rule)
+    }
+}
+
+// wager for each stakes tier in a native denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomStakes {
+    pub denom: String,
+    pub low_stakes: Uint128,
+    pub medium_stakes: Uint128,
+    pub high_stakes: Uint128,
+}
+
+// fields left as None are not changed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigUpdate {
+    pub red_weight: Option<u16>,
+    pub green_weight: Option<u16>,
+    pub blue_weight: Option<u16>,
+    pub black_weight: Option<u16>,
+    pub triangle_weight: Option<u16>,
+    pub square_weight: Option<u16>,
+    pub circle_weight: Option<u16>,
+    pub star_weight: Option<u16>,
+    pub low_stakes: Option<Uint128>,
+    pub medium_stakes: Option<Uint128>,
+    pub high_stakes: Option<Uint128>,
+    // replaces the other accepted native denoms, their pools and balances are kept
+    pub denoms: Option<Vec<DenomStakes>>,
+    pub timeout: Option<u64>,
+    pub rounds_per_game: Option<u8>,
+    pub lobby_policy: Option<String>,
+    pub lobby_expiration: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -103,15 +436,202 @@ pub enum ResponseStatus {
     Failure,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatus {
+    Normal,
+    StopTransactions,
+    StopAll,
+}
+
+impl ContractStatus {
+    /// Returns u8 representation of the ContractStatus
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            ContractStatus::Normal => 0,
+            ContractStatus::StopTransactions => 1,
+            ContractStatus::StopAll => 2,
+        }
+    }
+
+    pub fn from_u8(val: u8) -> StdResult<ContractStatus> {
+        match val {
+            0 => Ok(ContractStatus::Normal),
+            1 => Ok(ContractStatus::StopTransactions),
+            2 => Ok(ContractStatus::StopAll),
+            _ => Err(StdError::generic_err("Invalid contract status value")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundHistoryResponse {
+    pub round: u8,
+    pub chip_color: String,
+    pub chip_shape: String,
+    pub guess: Option<String>,
+    pub opponent_guess: Option<String>,
+    pub round_result: Option<String>,
+    pub opponent_round_result: Option<String>,
+}
+
+// whose move a transcript event is
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptPlayer {
+    You,
+    Opponent,
+}
+
+// an event in a game transcript, with the same typed values as v2. Opponent's submissions,
+// guesses and reward picks, and the extra secrets given to the opponent, are None until the game
+// is finished. turn is 1 or 2 for the first or second submission of a round
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptEvent {
+    Join {
+        player: TranscriptPlayer,
+        block: u64,
+        wager: Uint128,
+    },
+    Submit {
+        player: TranscriptPlayer,
+        round: u8,
+        turn: u8,
+        hint: Option<Hint>,
+        block: u64,
+    },
+    // secret given to player because the opponent's submission was provably false
+    ExtraSecret {
+        player: TranscriptPlayer,
+        round: u8,
+        turn: u8,
+        secret: Option<Hint>,
+        block: u64,
+    },
+    Guess {
+        player: TranscriptPlayer,
+        round: u8,
+        guess: Option<Guess>,
+        block: u64,
+    },
+    RewardPick {
+        player: TranscriptPlayer,
+        pick: Option<String>,
+        block: u64,
+    },
+    // player timed out and the opponent ended the game
+    Timeout {
+        player: TranscriptPlayer,
+        block: Option<u64>,
+    },
+    Payout {
+        player: TranscriptPlayer,
+        amount: Uint128,
+        denom: String,
+        block: Option<u64>,
+    },
+}
+
+// what was hidden from the player in a round, only given once the game is finished. Each
+// provably false flag is None if the submission was not made
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundRevealResponse {
+    pub round: u8,
+    pub bag_chip_color: String,
+    pub bag_chip_shape: String,
+    pub opponent_chip_color: String,
+    pub opponent_chip_shape: String,
+    pub opponent_hint: String,
+    pub first_submit_provably_false: Option<bool>,
+    pub second_submit_provably_false: Option<bool>,
+    pub opponent_first_submit_provably_false: Option<bool>,
+    pub opponent_second_submit_provably_false: Option<bool>,
+}
+
+// hex encoded sha256 commitment of a round's deal seed, the seed and the deal are only
+// revealed once the game is finished. deal_seed is sha256(contract_seed || sha256(player a entropy)
+// || sha256(player b entropy)), leaving out the hashes of players without entropy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DealResponse {
+    pub round: u8,
+    pub deal_commitment: String,
+    pub contract_seed: Option<String>,
+    pub deal_seed: Option<String>,
+    pub bag_chip_color: Option<String>,
+    pub bag_chip_shape: Option<String>,
+    pub player_a_chip_color: Option<String>,
+    pub player_a_chip_shape: Option<String>,
+    pub player_b_chip_color: Option<String>,
+    pub player_b_chip_shape: Option<String>,
+    pub player_a_first_hint: Option<String>,
+    pub player_b_first_hint: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardPlayerResponse {
+    pub rank: u32,
+    pub player: HumanAddr,
+    pub display_name: Option<String>,
+    pub games_played: u32,
+    pub wins: u32,
+    // wins per game played in basis points
+    pub win_rate: u32,
+    // signed integer string as it can be negative
+    pub net_winnings: String,
+}
+
+// rounds of a finished game, all chips are revealed once the game is over
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundSummaryResponse {
+    pub round: u8,
+    pub bag_chip_color: String,
+    pub bag_chip_shape: String,
+    pub chip_color: String,
+    pub chip_shape: String,
+    pub opponent_chip_color: String,
+    pub opponent_chip_shape: String,
+    pub guess: Option<String>,
+    pub opponent_guess: Option<String>,
+    pub round_result: Option<String>,
+    pub opponent_round_result: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameSummaryResponse {
+    pub game_idx: u32,
+    pub stakes: String,
+    pub private_game: bool,
+    pub result: String,
+    pub denom: String,
+    pub wager: Uint128,
+    pub opponent_wager: Option<Uint128>,
+    pub payout: Uint128,
+    // house rake taken from the player's payout
+    pub fees: Vec<FeeResponse>,
+    pub round_wins: u8,
+    pub opponent_round_wins: u8,
+    pub rounds: Vec<RoundSummaryResponse>,
+    pub jackpot_reward: Option<Uint128>,
+    pub nft_token_id: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct GameStateResponse {
     pub round: Option<u8>,
+    pub rounds_per_game: Option<u8>,
+    pub stakes: Option<String>,
+    pub private_game: Option<bool>,
+    pub round_wins: Option<u8>,
+    pub opponent_round_wins: Option<u8>,
+    pub round_history: Option<Vec<RoundHistoryResponse>>,
     pub wager: Option<Uint128>,
     pub chip_color: Option<String>,
     pub chip_shape: Option<String>,
     pub hint: Option<String>,
     pub powerup: Option<String>,
     pub first_round_start_block: Option<u64>,
+    pub deal_commitment: Option<String>,
     pub first_submit: Option<String>,
     pub first_submit_block: Option<u64>,
     pub opponent_first_submit: Option<String>,
@@ -134,6 +654,72 @@ pub struct GameStateResponse {
     pub pick: Option<String>,
     pub jackpot_reward: Option<Uint128>,
     pub nft_token_id: Option<String>,
+    pub reveal: Option<Vec<RoundRevealResponse>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundHistoryV2Response {
+    pub round: u8,
+    pub chip: Chip,
+    pub guess: Option<Guess>,
+    pub opponent_guess: Option<Guess>,
+    pub round_result: Option<RoundResult>,
+    pub opponent_round_result: Option<RoundResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundRevealV2Response {
+    pub round: u8,
+    pub bag_chip: Chip,
+    pub opponent_chip: Chip,
+    pub opponent_hint: Hint,
+    pub first_submit_provably_false: Option<bool>,
+    pub second_submit_provably_false: Option<bool>,
+    pub opponent_first_submit_provably_false: Option<bool>,
+    pub opponent_second_submit_provably_false: Option<bool>,
+}
+
+// GameStateResponse with typed values in place of the v1 strings. result is the game result
+// for both players, is_player_a tells which side of it the player is on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameStateV2Response {
+    pub round: Option<u8>,
+    pub rounds_per_game: Option<u8>,
+    pub stakes: Option<String>,
+    pub private_game: Option<bool>,
+    pub round_wins: Option<u8>,
+    pub opponent_round_wins: Option<u8>,
+    pub round_history: Option<Vec<RoundHistoryV2Response>>,
+    pub wager: Option<Uint128>,
+    pub chip: Option<Chip>,
+    pub hint: Option<Hint>,
+    pub powerup: Option<String>,
+    pub first_round_start_block: Option<u64>,
+    pub deal_commitment: Option<String>,
+    pub first_submit: Option<Hint>,
+    pub first_submit_block: Option<u64>,
+    pub opponent_first_submit: Option<Hint>,
+    pub first_extra_secret: Option<Hint>,
+    pub second_submit_turn_start_block: Option<u64>,
+    pub second_submit: Option<Hint>,
+    pub second_submit_block: Option<u64>,
+    pub opponent_second_submit: Option<Hint>,
+    pub second_extra_secret: Option<Hint>,
+    pub guess_turn_start_block: Option<u64>,
+    pub guess: Option<Guess>,
+    pub guess_block: Option<u64>,
+    pub opponent_guess: Option<Guess>,
+    pub round_result: Option<RoundResult>,
+    pub opponent_round_result: Option<RoundResult>,
+    pub pick_reward_round_start_block: Option<u64>,
+    pub finished: Option<bool>,
+    pub is_player_a: Option<bool>,
+    pub result: Option<GameResult>,
+    pub opponent_powerup: Option<String>,
+    pub pick: Option<String>,
+    pub jackpot_reward: Option<Uint128>,
+    pub nft_token_id: Option<String>,
+    pub reveal: Option<Vec<RoundRevealV2Response>>,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
@@ -142,6 +728,19 @@ pub enum HandleAnswer {
     Join {
         status: ResponseStatus,
         game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
+    },
+
+    CreatePrivateGame {
+        status: ResponseStatus,
+        game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
+    },
+
+    JoinPrivateGame {
+        status: ResponseStatus,
+        game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
     },
 
     Submit {
@@ -154,19 +753,38 @@ pub enum HandleAnswer {
         game_state: Option<GameStateResponse>,
     },
 
+    SubmitV2 {
+        status: ResponseStatus,
+        game_state: Option<GameStateV2Response>,
+    },
+
+    GuessV2 {
+        status: ResponseStatus,
+        game_state: Option<GameStateV2Response>,
+    },
+
     PickReward {
         status: ResponseStatus,
         game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
     },
 
     Withdraw {
         status: ResponseStatus,
         game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
+    },
+
+    ClaimRefund {
+        status: ResponseStatus,
+        game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
     },
 
     ForceEndgame {
         status: ResponseStatus,
         game_state: Option<GameStateResponse>,
+        game_state_v2: Option<GameStateV2Response>,
     },
 
     BatchReceiveNft {
@@ -178,6 +796,64 @@ pub enum HandleAnswer {
     RevokePermit {
         status: ResponseStatus,
     },
+
+    Claim {
+        status: ResponseStatus,
+        amount: Uint128,
+    },
+
+    Deposit {
+        status: ResponseStatus,
+        balance: Uint128,
+    },
+
+    SetLeaderboardOptIn {
+        status: ResponseStatus,
+    },
+
+    // Admin
+    UpdateConfig {
+        status: ResponseStatus,
+    },
+
+    ChangeAdmin {
+        status: ResponseStatus,
+    },
+
+    SetMinter {
+        status: ResponseStatus,
+    },
+
+    SetRake {
+        status: ResponseStatus,
+    },
+
+    SetFeeRouting {
+        status: ResponseStatus,
+    },
+
+    SetJackpotRule {
+        status: ResponseStatus,
+    },
+
+    FundPool {
+        status: ResponseStatus,
+        pool: Uint128,
+    },
+
+    ProposePoolWithdrawal {
+        status: ResponseStatus,
+        executable_block: u64,
+    },
+
+    ExecutePoolWithdrawal {
+        status: ResponseStatus,
+        pool: Uint128,
+    },
+
+    SetContractStatus {
+        status: ResponseStatus,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -185,6 +861,31 @@ pub enum HandleAnswer {
 pub enum QueryMsg {
     PoolSize { },
 
+    ContractStatus { },
+
+    // players who opted in, ranked by sort_by: wins (default), win_rate or net_winnings,
+    // page_size defaults to 10 and is capped at 50
+    Leaderboard {
+        page: Option<u32>,
+        page_size: Option<u32>,
+        sort_by: Option<String>,
+    },
+
+    // jackpot that picking the pool reward would pay out right now, defaults to the default denom.
+    // with game_idx, the jackpot in that game's denom after the rake the game was created with
+    Jackpot {
+        denom: Option<String>,
+        game_idx: Option<u32>,
+    },
+
+    // deal commitments of every round in a game, with the seeds and deals once the game is finished
+    VerifyDeal {
+        game_idx: u32,
+    },
+
+    // pool withdrawals the admin has proposed and not executed yet
+    PendingWithdrawals { },
+
     WithPermit {
         permit: Permit,
         query: QueryWithPermit,
@@ -197,26 +898,95 @@ pub enum QueryWithPermit {
     // GameState returns the player's view on current game
     GameState {},
 
-    // PlayerStats returns how many wins/losses for player
+    // GameStateV2 is GameState with typed chips, hints, guesses and results
+    GameStateV2 {},
+
+    // PlayerStats returns the player's game outcomes and winnings over all games
     PlayerStats {},
+
+    // Balance returns the player's claimable refunds and winnings
+    Balance {},
+
+    // GameHistory returns summaries of the player's finished games, newest first,
+    // page_size defaults to 10 and is capped at 50
+    GameHistory {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+
+    // GameTranscript returns everything that happened in one of the player's games, in order
+    GameTranscript {
+        game_idx: u32,
+    },
+
+    // PoolLog returns every change to the jackpot pools, newest first, admin only,
+    // page_size defaults to 10 and is capped at 50
+    PoolLog {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+
+    // Solvency reconciles what the contract owes against its balance in each denom, admin only
+    Solvency {},
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
+    Jackpot {
+        denom: String,
+        pool: Uint128,
+        rule: JackpotRule,
+        // jackpot before and after the house rake on new games, or on the game asked about
+        amount: Uint128,
+        after_rake: Uint128,
+    },
+
     PoolSize {
+        // pool of the default denom
         amount: Uint128,
         denom: String,
+        // pools of every accepted denom
+        pools: Vec<Coin>,
+    },
+
+    ContractStatus {
+        status: ContractStatus,
+    },
+
+    Leaderboard {
+        // number of players on the leaderboard
+        total: u32,
+        players: Vec<LeaderboardPlayerResponse>,
+    },
+
+    VerifyDeal {
+        finished: bool,
+        // hex sha256 of each player's entropy, if they gave any
+        player_a_entropy_hash: Option<String>,
+        player_b_entropy_hash: Option<String>,
+        // red, green, blue, black
+        color_weights: Vec<u16>,
+        // triangle, square, circle, star
+        shape_weights: Vec<u16>,
+        rounds: Vec<DealResponse>,
     },
 
     GameState {
         round: Option<u8>,
+        rounds_per_game: Option<u8>,
+        stakes: Option<String>,
+        private_game: Option<bool>,
+        round_wins: Option<u8>,
+        opponent_round_wins: Option<u8>,
+        round_history: Option<Vec<RoundHistoryResponse>>,
         wager: Option<Uint128>,
         chip_color: Option<String>,
         chip_shape: Option<String>,
         hint: Option<String>,
         powerup: Option<String>,
         first_round_start_block: Option<u64>,
+        deal_commitment: Option<String>,
         first_submit: Option<String>,
         first_submit_block: Option<u64>,
         opponent_first_submit: Option<String>,
@@ -239,10 +1009,65 @@ pub enum QueryAnswer {
         pick: Option<String>,
         jackpot_reward: Option<Uint128>,
         nft_token_id: Option<String>,
+        reveal: Option<Vec<RoundRevealResponse>>,
     },
 
     PlayerStats {
-        info: String // TODO:
+        games_played: u32,
+        wins: u32,
+        losses: u32,
+        both_lose: u32,
+        ties: u32,
+        jackpots_won: u32,
+        nfts_won: u32,
+        no_rewards: u32,
+        timeout_wins: u32,
+        timeout_losses: u32,
+        withdrawals: u32,
+        total_wagered: Uint128,
+        total_payout: Uint128,
+        // total_payout - total_wagered, a signed integer string as it can be negative
+        net_winnings: String,
+    },
+
+    Balance {
+        // balance in the default denom
+        amount: Uint128,
+        denom: String,
+        // balances in every accepted denom
+        balances: Vec<Coin>,
+    },
+
+    GameHistory {
+        // number of finished games
+        total: u32,
+        games: Vec<GameSummaryResponse>,
+    },
+
+    GameTranscript {
+        game_idx: u32,
+        finished: bool,
+        events: Vec<TranscriptEvent>,
+    },
+
+    GameStateV2 {
+        game_state: GameStateV2Response,
+    },
+
+    PoolLog {
+        // number of pool changes
+        total: u32,
+        changes: Vec<PoolChangeResponse>,
+    },
+
+    Solvency {
+        denoms: Vec<SolvencyResponse>,
+    },
+
+    PendingWithdrawals {
+        // # of blocks between proposing and executing a withdrawal
+        delay: u64,
+        withdrawals: Vec<PendingWithdrawalResponse>,
     },
 }
 
@@ -6,11 +6,47 @@ use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 use sha2::{Digest, Sha256};
 
-use crate::state::{get_config, Config};
+use crate::state::{ChipWeights};
 use crate::types::{Color, Shape};
 
 static KEY_ENTROPY_POOL: &[u8] = b"entropy_pool";
 
+/// Deterministic stream of random numbers, ChaCha20 seeded with sha256(seed || entropy). The
+/// same seed and entropy always give the same stream, so successive draws must come from the
+/// same instance rather than from a new one.
+pub struct Prng {
+    rng: ChaChaRng,
+}
+
+impl Prng {
+    pub fn new(seed: &[u8], entropy: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+
+        // write input message
+        hasher.update(&seed);
+        hasher.update(&entropy);
+        let hash = hasher.finalize();
+
+        let mut hash_bytes = [0u8; 32];
+        hash_bytes.copy_from_slice(hash.as_slice());
+
+        let rng: ChaChaRng = ChaChaRng::from_seed(hash_bytes);
+
+        Self { rng }
+    }
+
+    pub fn rand_bytes(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.rng.fill_bytes(&mut bytes);
+
+        bytes
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
 fn get_current_entropy_pool<S: Storage>(storage: &S) -> [u8; 32] {
     ReadonlySingleton::new(storage, KEY_ENTROPY_POOL)
         .load()
@@ -21,33 +57,34 @@ fn get_current_entropy_pool<S: Storage>(storage: &S) -> [u8; 32] {
         .unwrap()
 }
 
+/// Mixes the entropy into the pool and returns the transaction's random stream, seeded from
+/// the new pool. Every draw in the transaction is taken from the returned rng.
 pub fn supply_more_entropy<S: Storage>(
     storage: &mut S,
     additional_entropy: &[u8],
-) -> StdResult<()> {
+) -> StdResult<Prng> {
     let current_entropy_pool = get_current_entropy_pool(storage);
-    println!("{:?}", current_entropy_pool);
 
     let mut new_entropy_source = Vec::from(current_entropy_pool);
     new_entropy_source.extend(additional_entropy);
 
     let new_entropy_pool: [u8; 32] = Sha256::digest(&new_entropy_source).into();
 
-    Singleton::new(storage, KEY_ENTROPY_POOL).save(&new_entropy_pool)
+    Singleton::new(storage, KEY_ENTROPY_POOL).save(&new_entropy_pool)?;
+
+    Ok(Prng::new(&new_entropy_pool, &[]))
 }
 
-pub fn get_random_color<S: Storage>(storage: &S, color_options: &mut Vec<Color>, remove: bool) -> StdResult<Option<Color>> {
+pub fn get_random_color(rng: &mut Prng, weights: &ChipWeights, color_options: &mut Vec<Color>, remove: bool) -> StdResult<Option<Color>> {
     if color_options.len() == 0 {
         return Err(StdError::generic_err("No color options when picking a random color"));
     }
 
-    let config: Config = get_config(storage)?;
-
     let color_percentage_map: HashMap<Color, u64> = [
-        (Color::Red, config.red_weight as u64),
-        (Color::Green, config.green_weight as u64),
-        (Color::Blue, config.blue_weight as u64),
-        (Color::Black, config.black_weight as u64),
+        (Color::Red, weights.red_weight as u64),
+        (Color::Green, weights.green_weight as u64),
+        (Color::Blue, weights.blue_weight as u64),
+        (Color::Black, weights.black_weight as u64),
     ].iter().cloned().collect();
 
     let mut total = 0_u64;
@@ -61,8 +98,7 @@ pub fn get_random_color<S: Storage>(storage: &S, color_options: &mut Vec<Color>,
     }
     debug_print(format!("color weight total: {}", total));
 
-    let roll = get_random_number(storage) % total;
-    println!("{}", roll);
+    let roll = rng.next_u64() % total;
     debug_print(format!("color roll: {}", roll));
 
     let mut interval_start = 0_u64;
@@ -85,18 +121,16 @@ pub fn get_random_color<S: Storage>(storage: &S, color_options: &mut Vec<Color>,
     Ok(picked_color)
 }
 
-pub fn get_random_shape<S: Storage>(storage: &S, shape_options: &mut Vec<Shape>, remove: bool) -> StdResult<Option<Shape>> {
+pub fn get_random_shape(rng: &mut Prng, weights: &ChipWeights, shape_options: &mut Vec<Shape>, remove: bool) -> StdResult<Option<Shape>> {
     if shape_options.len() == 0 {
         return Err(StdError::generic_err("No shape options when picking a random shape"));
     }
 
-    let config: Config = get_config(storage)?;
-
     let shape_percentage_map: HashMap<Shape, u64> = [
-        (Shape::Triangle, config.triangle_weight as u64),
-        (Shape::Square, config.square_weight as u64),
-        (Shape::Circle, config.circle_weight as u64),
-        (Shape::Star, config.star_weight as u64),
+        (Shape::Triangle, weights.triangle_weight as u64),
+        (Shape::Square, weights.square_weight as u64),
+        (Shape::Circle, weights.circle_weight as u64),
+        (Shape::Star, weights.star_weight as u64),
     ].iter().cloned().collect();
 
     let mut total = 0_u64;
@@ -110,7 +144,7 @@ pub fn get_random_shape<S: Storage>(storage: &S, shape_options: &mut Vec<Shape>,
     }
     debug_print(format!("shape weight total: {}", total));
 
-    let roll = get_random_number(storage) % total;
+    let roll = rng.next_u64() % total;
     debug_print(format!("shape roll: {}", roll));
 
     let mut interval_start = 0_u64;
@@ -133,14 +167,6 @@ pub fn get_random_shape<S: Storage>(storage: &S, shape_options: &mut Vec<Shape>,
     Ok(picked_shape)
 }
 
-pub fn get_random_number<S: Storage>(storage: &S) -> u64 {
-    let entropy_pool = get_current_entropy_pool(storage);
-
-    let mut rng = ChaChaRng::from_seed(entropy_pool);
-
-    rng.next_u64()
-}
-
 pub fn sha_256(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -151,3 +177,178 @@ pub fn sha_256(data: &[u8]) -> [u8; 32] {
     result
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use crate::state::{create_new_round, deal_round};
+    use crate::types::Hint;
+
+    // chi-squared critical values at p = 0.001
+    const CHI_SQUARED_3_DF: f64 = 16.27;
+    const CHI_SQUARED_7_DF: f64 = 24.32;
+    const CHI_SQUARED_15_DF: f64 = 37.70;
+
+    const TRANSACTIONS: u64 = 4000;
+
+    fn chi_squared(counts: &[u64], expected: f64) -> f64 {
+        counts.iter().map(|count| {
+            let diff = *count as f64 - expected;
+            diff * diff / expected
+        }).sum()
+    }
+
+    fn equal_weights() -> ChipWeights {
+        ChipWeights {
+            red_weight: 25,
+            green_weight: 25,
+            blue_weight: 25,
+            black_weight: 25,
+            triangle_weight: 25,
+            square_weight: 25,
+            circle_weight: 25,
+            star_weight: 25,
+        }
+    }
+
+    #[test]
+    fn test_draws_in_transaction_differ() {
+        let mut storage = MockStorage::new();
+        let mut rng = supply_more_entropy(&mut storage, b"foo").unwrap();
+        let draws: Vec<u64> = (0..20).map(|_| rng.next_u64()).collect();
+        for i in 0..draws.len() {
+            for j in (i + 1)..draws.len() {
+                assert_ne!(draws[i], draws[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_restarts_with_new_entropy() {
+        let mut storage_1 = MockStorage::new();
+        let mut storage_2 = MockStorage::new();
+        let mut rng_1 = supply_more_entropy(&mut storage_1, b"foo").unwrap();
+        let mut rng_2 = supply_more_entropy(&mut storage_2, b"foo").unwrap();
+        let draw_1 = rng_1.next_u64();
+        assert_eq!(draw_1, rng_2.next_u64());
+        assert_eq!(rng_1.next_u64(), rng_2.next_u64());
+
+        let mut rng_1 = supply_more_entropy(&mut storage_1, b"bar!").unwrap();
+        assert_ne!(draw_1, rng_1.next_u64());
+    }
+
+    #[test]
+    fn test_draws_are_uniform() {
+        let mut storage = MockStorage::new();
+        let mut counts = [0_u64; 4];
+        for tx in 0..TRANSACTIONS {
+            let mut rng = supply_more_entropy(&mut storage, &tx.to_be_bytes()).unwrap();
+            counts[(rng.next_u64() % 4) as usize] += 1;
+        }
+        let expected = TRANSACTIONS as f64 / 4.0;
+        assert!(chi_squared(&counts, expected) < CHI_SQUARED_3_DF);
+    }
+
+    #[test]
+    fn test_consecutive_draws_are_independent() {
+        let mut storage = MockStorage::new();
+        let mut counts = [0_u64; 16];
+        for tx in 0..TRANSACTIONS {
+            let mut rng = supply_more_entropy(&mut storage, &tx.to_be_bytes()).unwrap();
+            let first = rng.next_u64() % 4;
+            let second = rng.next_u64() % 4;
+            counts[(first * 4 + second) as usize] += 1;
+        }
+        let expected = TRANSACTIONS as f64 / 16.0;
+        assert!(chi_squared(&counts, expected) < CHI_SQUARED_15_DF);
+
+        // correlated draws would pile up on the diagonal
+        let same: u64 = (0..4).map(|i| counts[i * 5]).sum();
+        assert!(same < TRANSACTIONS / 4 + TRANSACTIONS / 20);
+    }
+
+    #[test]
+    fn test_picked_colors_are_independent() {
+        let mut storage = MockStorage::new();
+        let weights = equal_weights();
+        let all_colors = vec![Color::Red, Color::Green, Color::Blue, Color::Black];
+        let mut counts = [0_u64; 16];
+        for tx in 0..TRANSACTIONS {
+            let mut tx_rng = supply_more_entropy(&mut storage, &tx.to_be_bytes()).unwrap();
+            let mut rng = Prng::new(&tx_rng.rand_bytes(), &[]);
+            let first = get_random_color(&mut rng, &weights, &mut all_colors.clone(), false).unwrap().unwrap();
+            let second = get_random_color(&mut rng, &weights, &mut all_colors.clone(), false).unwrap().unwrap();
+            counts[(first.u8_val() * 4 + second.u8_val()) as usize] += 1;
+        }
+        let expected = TRANSACTIONS as f64 / 16.0;
+        assert!(chi_squared(&counts, expected) < CHI_SQUARED_15_DF);
+    }
+
+    #[test]
+    fn test_hint_roll_independent_of_bag_chip() {
+        let mut storage = MockStorage::new();
+        let weights = equal_weights();
+        let mut counts = [0_u64; 8];
+        for tx in 0..TRANSACTIONS {
+            let mut rng = supply_more_entropy(&mut storage, &tx.to_be_bytes()).unwrap();
+            let round_state = create_new_round(&mut rng, tx, &weights, &[]).unwrap();
+            let bag_color = Color::from_u8(round_state.bag_chip.color).unwrap().u8_val();
+            let color_hint = Hint::from_u8(round_state.player_a_first_hint).unwrap().is_color();
+            counts[(bag_color * 2 + color_hint as u8) as usize] += 1;
+        }
+        let expected = TRANSACTIONS as f64 / 8.0;
+        assert!(chi_squared(&counts, expected) < CHI_SQUARED_7_DF);
+    }
+
+    #[test]
+    fn test_deal_repeats_from_seed() {
+        let mut storage = MockStorage::new();
+        let weights = equal_weights();
+        let mut rng = supply_more_entropy(&mut storage, b"foo").unwrap();
+        let round_state = create_new_round(&mut rng, 1, &weights, &sha_256(b"bar!")).unwrap();
+        assert_eq!(round_state.deal_commitment, sha_256(&round_state.deal_seed).to_vec());
+        let mut seed_source = round_state.contract_seed.clone();
+        seed_source.extend(&sha_256(b"bar!"));
+        assert_eq!(round_state.deal_seed, sha_256(&seed_source).to_vec());
+
+        let deal = deal_round(&round_state.deal_seed, &weights).unwrap();
+        assert_eq!(deal.bag_chip.to_stored().color, round_state.bag_chip.color);
+        assert_eq!(deal.bag_chip.to_stored().shape, round_state.bag_chip.shape);
+        assert_eq!(deal.player_a_chip.to_stored().color, round_state.player_a_chip.color);
+        assert_eq!(deal.player_a_chip.to_stored().shape, round_state.player_a_chip.shape);
+        assert_eq!(deal.player_b_chip.to_stored().color, round_state.player_b_chip.color);
+        assert_eq!(deal.player_b_chip.to_stored().shape, round_state.player_b_chip.shape);
+        assert_eq!(deal.player_a_first_hint.u8_val(), round_state.player_a_first_hint);
+        assert_eq!(deal.player_b_first_hint.u8_val(), round_state.player_b_first_hint);
+    }
+
+    #[test]
+    fn test_deal_does_not_repeat_from_commitment() {
+        let mut storage = MockStorage::new();
+        let weights = equal_weights();
+        let mut repeated = 0;
+        for tx in 0..100_u64 {
+            let mut rng = supply_more_entropy(&mut storage, &tx.to_be_bytes()).unwrap();
+            let round_state = create_new_round(&mut rng, tx, &weights, &[]).unwrap();
+            // the deal rng's stream is not the one seeded with the commitment itself
+            let mut commitment = [0u8; 32];
+            commitment.copy_from_slice(&round_state.deal_commitment);
+            let mut from_commitment = ChaChaRng::from_seed(commitment);
+            let mut from_seed = Prng::new(&round_state.deal_seed, b"deal");
+            assert_ne!(from_seed.next_u64(), from_commitment.next_u64());
+
+            let deal = deal_round(&round_state.deal_commitment, &weights).unwrap();
+            if deal.bag_chip.to_stored().color == round_state.bag_chip.color &&
+               deal.bag_chip.to_stored().shape == round_state.bag_chip.shape &&
+               deal.player_a_chip.to_stored().color == round_state.player_a_chip.color &&
+               deal.player_a_chip.to_stored().shape == round_state.player_a_chip.shape &&
+               deal.player_b_chip.to_stored().color == round_state.player_b_chip.color &&
+               deal.player_b_chip.to_stored().shape == round_state.player_b_chip.shape {
+                repeated += 1;
+            }
+        }
+        // a deal is one of 4*4*3*3*2*2 = 576 equally likely ones with equal weights
+        assert!(repeated < 3);
+    }
+}
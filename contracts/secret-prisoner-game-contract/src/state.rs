@@ -1,23 +1,33 @@
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
+use std::cmp::min;
 use cosmwasm_std::{
-    CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage, Api,
+    CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage, Api, Uint128,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use secret_toolkit::storage::{AppendStore, AppendStoreMut};
-use crate::types::{Color, RED, GREEN, BLUE, BLACK, TRIANGLE, SQUARE, CIRCLE, STAR, Shape, Chip, RoundStage, Hint, StoredChip, StoredGuess};
-use crate::random::{get_random_color, get_random_shape, get_random_number};
-use crate::msg::{ContractInfo};
+use crate::types::{StakesTier, Color, RED, GREEN, BLUE, BLACK, TRIANGLE, SQUARE, CIRCLE, STAR, Shape, Chip, RoundStage, RoundResult, RoundOutcome, PlayerOutcome, PoolChangeReason, Hint, StoredChip, StoredGuess};
+use crate::random::{get_random_color, get_random_shape, sha_256, Prng};
+use crate::msg::{ContractInfo, JackpotRule, JackpotTier};
+use crate::error::ContractError;
 
 pub static CONFIG_KEY: &[u8] = b"config";
 pub static MINTER_KEY: &[u8] = b"minter";
-pub static POOL_KEY: &[u8] = b"pool";
+pub static POOL_PREFIX: &[u8] = b"pool";
+pub static POOL_LOG_PREFIX: &[u8] = b"pool-log";
+pub static PENDING_WITHDRAWAL_PREFIX: &[u8] = b"pending-withdrawal";
+pub static ESCROW_PREFIX: &[u8] = b"escrow";
+pub static BALANCE_TOTAL_PREFIX: &[u8] = b"balance-total";
 pub static GAME_PREFIX: &[u8] = b"game";
 pub static PLAYER_PREFIX: &[u8] = b"player";
 pub static CURRENT_GAME_PREFIX: &[u8] = b"current-game";
-pub static WON_PREFIX: &[u8] = b"won";
-pub static LOST_PREFIX: &[u8] = b"lost";
+pub static LOBBY_PREFIX: &[u8] = b"lobby";
+pub static PLAYER_STATS_PREFIX: &[u8] = b"player-stats";
+pub static PLAYER_GAMES_PREFIX: &[u8] = b"player-games";
+pub static LEADERBOARD_PREFIX: &[u8] = b"leaderboard";
+pub static LEADERBOARD_ENTRY_PREFIX: &[u8] = b"leaderboard-entry";
+pub static BALANCE_PREFIX: &[u8] = b"balance";
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
@@ -31,10 +41,33 @@ pub struct Config {
     pub square_weight: u16,
     pub circle_weight: u16,
     pub star_weight: u16,
-    pub stakes: u128,
+    // accepted denoms with the wager for each of their stakes tiers, the first one is the
+    // default denom (uscrt, or the address of the snip-20 token if one is configured)
+    pub denoms: Vec<DenomConfig>,
     pub timeout: u64,
+    // number of assertion/guessing rounds before the reward round
+    pub rounds_per_game: u8,
+    // how open games in the lobby are paired, and # of blocks before they expire
+    pub lobby_policy: u8,
+    pub lobby_expiration: u64,
+    // ContractStatus as u8
+    pub status: u8,
     // viewing key for minter contract
     pub viewing_key: String,
+    // snip-20 token wagers are made in, None for uscrt
+    pub token: Option<StoreContractInfo>,
+    // house rake on winning payouts in basis points
+    pub rake_bps: u16,
+    // how each rake is split in basis points, the referral share goes to the winner's referrer,
+    // or the treasury if they have none, and the treasury share stays in the pool without a treasury
+    pub rake_pool_bps: u16,
+    pub rake_treasury_bps: u16,
+    pub rake_referral_bps: u16,
+    pub treasury: Option<CanonicalAddr>,
+    // how much of the pool is paid out when a jackpot is picked
+    pub jackpot_rule: StoredJackpotRule,
+    // # of blocks between proposing and executing a pool withdrawal
+    pub pool_withdrawal_delay: u64,
 }
 
 pub fn set_config<S: Storage>(
@@ -48,6 +81,101 @@ pub fn get_config<S: ReadonlyStorage>(storage: &S) -> StdResult<Config> {
     get_bin_data(storage, CONFIG_KEY)
 }
 
+impl Config {
+    pub fn chip_weights(&self) -> ChipWeights {
+        ChipWeights {
+            red_weight: self.red_weight,
+            green_weight: self.green_weight,
+            blue_weight: self.blue_weight,
+            black_weight: self.black_weight,
+            triangle_weight: self.triangle_weight,
+            square_weight: self.square_weight,
+            circle_weight: self.circle_weight,
+            star_weight: self.star_weight,
+        }
+    }
+
+    pub fn default_denom(&self) -> &str {
+        &self.denoms[0].denom
+    }
+
+    pub fn denom_config(&self, denom: &str) -> Option<&DenomConfig> {
+        self.denoms.iter().find(|denom_config| denom_config.denom == denom)
+    }
+
+    pub fn stakes_for_tier(&self, denom: &str, stakes: &StakesTier) -> Result<u128, ContractError> {
+        let denom_config = match self.denom_config(denom) {
+            Some(denom_config) => denom_config,
+            None => return Err(ContractError::DenomNotAccepted { denom: denom.to_string() }),
+        };
+        Ok(match stakes {
+            StakesTier::Practice => 0_u128,
+            StakesTier::Low => denom_config.low_stakes,
+            StakesTier::Medium => denom_config.medium_stakes,
+            StakesTier::High => denom_config.high_stakes,
+        })
+    }
+}
+
+/// rule for the jackpot paid out of a pool, amounts are in the pool's denom
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum StoredJackpotRule {
+    Fraction { bps: u16 },
+    CappedFraction { bps: u16, cap: u128 },
+    Reserve { bps: u16, min_reserve: u128 },
+    // (min_pool, bps) ordered by min_pool, the highest tier the pool has reached applies
+    Tiers { tiers: Vec<(u128, u16)> },
+}
+
+impl StoredJackpotRule {
+    pub fn to_humanized(&self) -> JackpotRule {
+        match self {
+            StoredJackpotRule::Fraction { bps } => JackpotRule::Fraction { bps: *bps },
+            StoredJackpotRule::CappedFraction { bps, cap } => JackpotRule::CappedFraction { bps: *bps, cap: Uint128(*cap) },
+            StoredJackpotRule::Reserve { bps, min_reserve } => JackpotRule::Reserve { bps: *bps, min_reserve: Uint128(*min_reserve) },
+            StoredJackpotRule::Tiers { tiers } => JackpotRule::Tiers {
+                tiers: tiers.iter().map(|(min_pool, bps)| JackpotTier { min_pool: Uint128(*min_pool), bps: *bps }).collect(),
+            },
+        }
+    }
+
+    /// Returns the jackpot paid out of a pool of `pool`
+    pub fn jackpot(&self, pool: u128) -> u128 {
+        let fraction = |bps: u16| pool * bps as u128 / 10000;
+        match self {
+            StoredJackpotRule::Fraction { bps } => fraction(*bps),
+            StoredJackpotRule::CappedFraction { bps, cap } => min(fraction(*bps), *cap),
+            StoredJackpotRule::Reserve { bps, min_reserve } => min(fraction(*bps), pool.saturating_sub(*min_reserve)),
+            StoredJackpotRule::Tiers { tiers } => match tiers.iter().rev().find(|(min_pool, _)| pool >= *min_pool) {
+                Some((_, bps)) => fraction(*bps),
+                None => 0,
+            },
+        }
+    }
+}
+
+/// wager for each stakes tier in an accepted denom, practice tier is always 0
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DenomConfig {
+    pub denom: String,
+    pub low_stakes: u128,
+    pub medium_stakes: u128,
+    pub high_stakes: u128,
+}
+
+/// color and shape weights used when dealing chips
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChipWeights {
+    pub red_weight: u16,
+    pub green_weight: u16,
+    pub blue_weight: u16,
+    pub black_weight: u16,
+    pub triangle_weight: u16,
+    pub square_weight: u16,
+    pub circle_weight: u16,
+    pub star_weight: u16,
+}
+
 /// code hash and address of a contract
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StoreContractInfo {
@@ -87,32 +215,263 @@ pub fn get_minter<S: ReadonlyStorage>(
 }
 
 ///
-/// Pool size
+/// Pool size, one jackpot pool for each denom. Every change is appended to the pool log
 /// 
 
-pub fn set_pool<S: Storage>(
+fn set_pool<S: Storage>(
     storage: &mut S,
+    denom: &str,
     amount: u128,
 ) -> StdResult<()> {
-    set_bin_data(storage, POOL_KEY, &amount)
+    let mut storage = PrefixedStorage::new(POOL_PREFIX, storage);
+    set_bin_data(&mut storage, denom.as_bytes(), &amount)
 }
 
 pub fn get_pool<S: ReadonlyStorage>(
     storage: &S,
+    denom: &str,
 ) -> StdResult<u128> {
-    get_bin_data(storage, POOL_KEY)
+    let storage = ReadonlyPrefixedStorage::new(POOL_PREFIX, storage);
+    Ok(get_bin_data(&storage, denom.as_bytes()).unwrap_or_else(|_| 0_u128))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PoolChange {
+    pub denom: String,
+    pub added: u128,
+    pub removed: u128,
+    // pool size after the change
+    pub pool: u128,
+    // PoolChangeReason as u8
+    pub reason: u8,
+    pub game_idx: Option<u32>,
+    pub block: u64,
+}
+
+fn change_pool<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    added: u128,
+    removed: u128,
+    reason: PoolChangeReason,
+    game_idx: Option<u32>,
+    block: u64,
+) -> Result<(), ContractError> {
+    if added == 0 && removed == 0 {
+        return Ok(());
+    }
+    let pool = get_pool(storage, denom)?;
+    if removed > pool + added {
+        return Err(ContractError::PoolTooSmall { denom: denom.to_string(), pool: Uint128(pool) });
+    }
+    let pool = pool + added - removed;
+    set_pool(storage, denom, pool)?;
+
+    let change = PoolChange { denom: denom.to_string(), added, removed, pool, reason: reason.u8_val(), game_idx, block };
+    let mut storage = PrefixedStorage::new(POOL_LOG_PREFIX, storage);
+    let mut storage = AppendStoreMut::<PoolChange, _>::attach_or_create(&mut storage)?;
+    Ok(storage.push(&change)?)
+}
+
+pub fn add_to_pool<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    amount: u128,
+    reason: PoolChangeReason,
+    game_idx: Option<u32>,
+    block: u64,
+) -> Result<(), ContractError> {
+    change_pool(storage, denom, amount, 0, reason, game_idx, block)
+}
+
+pub fn take_from_pool<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    amount: u128,
+    reason: PoolChangeReason,
+    game_idx: Option<u32>,
+    block: u64,
+) -> Result<(), ContractError> {
+    change_pool(storage, denom, 0, amount, reason, game_idx, block)
+}
+
+/// Returns up to `take` pool changes, newest first, after skipping the `skip` newest ones,
+/// along with the total number of changes.
+pub fn get_pool_log<S: ReadonlyStorage>(
+    storage: &S,
+    skip: u32,
+    take: u32,
+) -> StdResult<(Vec<PoolChange>, u32)> {
+    let storage = ReadonlyPrefixedStorage::new(POOL_LOG_PREFIX, storage);
+    let storage = match AppendStore::<PoolChange, _>::attach(&storage) {
+        Some(result) => result?,
+        None => return Ok((vec![], 0)),
+    };
+    let changes: StdResult<Vec<PoolChange>> = storage
+        .iter()
+        .rev()
+        .skip(skip as usize)
+        .take(take as usize)
+        .collect();
+    Ok((changes?, storage.len()))
+}
+
+///
+/// Pending pool withdrawals proposed by the admin, at most one per denom
+///
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingWithdrawal {
+    pub amount: u128,
+    pub recipient: CanonicalAddr,
+    pub proposed_block: u64,
+    // first block height the withdrawal can be executed at
+    pub executable_block: u64,
+}
+
+pub fn set_pending_withdrawal<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    withdrawal: &PendingWithdrawal,
+) -> StdResult<()> {
+    let mut storage = PrefixedStorage::new(PENDING_WITHDRAWAL_PREFIX, storage);
+    set_bin_data(&mut storage, denom.as_bytes(), withdrawal)
+}
+
+pub fn get_pending_withdrawal<S: ReadonlyStorage>(
+    storage: &S,
+    denom: &str,
+) -> Option<PendingWithdrawal> {
+    let storage = ReadonlyPrefixedStorage::new(PENDING_WITHDRAWAL_PREFIX, storage);
+    get_bin_data(&storage, denom.as_bytes()).ok()
+}
+
+pub fn remove_pending_withdrawal<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+) {
+    let mut storage = PrefixedStorage::new(PENDING_WITHDRAWAL_PREFIX, storage);
+    storage.remove(denom.as_bytes());
+}
+
+///
+/// Escrow, wagers of games that have not finished yet
+///
+
+pub fn get_escrow<S: ReadonlyStorage>(
+    storage: &S,
+    denom: &str,
+) -> u128 {
+    let storage = ReadonlyPrefixedStorage::new(ESCROW_PREFIX, storage);
+    get_bin_data(&storage, denom.as_bytes()).unwrap_or_else(|_| 0_u128)
+}
+
+fn set_escrow<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    amount: u128,
+) -> StdResult<()> {
+    let mut storage = PrefixedStorage::new(ESCROW_PREFIX, storage);
+    set_bin_data(&mut storage, denom.as_bytes(), &amount)
+}
+
+pub fn add_escrow<S: Storage>(
+    storage: &mut S,
+    denom: &str,
+    amount: u128,
+) -> StdResult<()> {
+    let escrow = get_escrow(storage, denom);
+    set_escrow(storage, denom, escrow + amount)
+}
+
+/// Releases the wagers of a game from escrow once it is finished, call exactly once per game
+pub fn release_escrow<S: Storage>(
+    storage: &mut S,
+    game_state: &GameState,
+) -> StdResult<()> {
+    let mut wagers = game_state.player_a_wager.unwrap_or(0);
+    if game_state.player_b.is_some() {
+        wagers = wagers + game_state.player_b_wager.unwrap_or(0);
+    }
+    let escrow = get_escrow(storage, &game_state.denom);
+    set_escrow(storage, &game_state.denom, escrow.saturating_sub(wagers))
+}
+
+///
+/// Claimable balances in each denom, refunds and winnings are credited here and sent out by Claim
+///
+
+pub fn set_balance<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    denom: &str,
+    balance: u128,
+) -> StdResult<()> {
+    // keep the total of all balances in the denom up to date
+    let total = get_balance_total(storage, denom) - get_balance(storage, player, denom) + balance;
+    let mut total_storage = PrefixedStorage::new(BALANCE_TOTAL_PREFIX, storage);
+    set_bin_data(&mut total_storage, denom.as_bytes(), &total)?;
+
+    let mut storage = PrefixedStorage::multilevel(&[BALANCE_PREFIX, denom.as_bytes()], storage);
+    set_bin_data(&mut storage, player.as_slice(), &balance)
+}
+
+/// Returns the total of all claimable balances in the denom
+pub fn get_balance_total<S: ReadonlyStorage>(
+    storage: &S,
+    denom: &str,
+) -> u128 {
+    let storage = ReadonlyPrefixedStorage::new(BALANCE_TOTAL_PREFIX, storage);
+    get_bin_data(&storage, denom.as_bytes()).unwrap_or_else(|_| 0_u128)
+}
+
+pub fn get_balance<S: ReadonlyStorage>(
+    storage: &S,
+    player: &CanonicalAddr,
+    denom: &str,
+) -> u128 {
+    let storage = ReadonlyPrefixedStorage::multilevel(&[BALANCE_PREFIX, denom.as_bytes()], storage);
+    get_bin_data(&storage, player.as_slice()).unwrap_or_else(|_| 0_u128)
+}
+
+pub fn credit_balance<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    denom: &str,
+    amount: u128,
+) -> StdResult<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let balance = get_balance(storage, player, denom);
+    set_balance(storage, player, denom, balance + amount)
 }
 
 ///
 /// Game state
 ///
 
+/// chips and first hints dealt at the start of a round
+pub struct Deal {
+    pub bag_chip: Chip,
+    pub player_a_chip: Chip,
+    pub player_b_chip: Chip,
+    pub player_a_first_hint: Hint,
+    pub player_b_first_hint: Hint,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct RoundState {
     pub stage: u8,
     // block height when the round started
     pub round_start_block: u64,
 
+    // seed the chips were dealt from, kept secret until the game is finished, and its sha256 hash.
+    // deal seed is sha256 of the contract's seed followed by the players' entropy
+    pub contract_seed: Vec<u8>,
+    pub deal_seed: Vec<u8>,
+    pub deal_commitment: Vec<u8>,
+
     pub bag_chip: StoredChip,
     pub player_a_chip: StoredChip,
     pub player_b_chip: StoredChip,
@@ -152,8 +511,21 @@ pub struct GameState {
     pub player_a: CanonicalAddr,
     pub player_b: Option<CanonicalAddr>,
 
+    // private games can only be joined by the invited player and/or with the invite code
+    pub invited_player: Option<CanonicalAddr>,
+    pub invite_hash: Option<Vec<u8>>,
+
+    // stakes tier and denom the game was joined with
+    pub stakes: u8,
+    pub denom: String,
     pub player_a_wager: Option<u128>,
     pub player_b_wager: Option<u128>,
+    // sha256 of entropy each player gave when joining, mixed into every deal
+    pub player_a_entropy: Option<Vec<u8>>,
+    pub player_b_entropy: Option<Vec<u8>>,
+    // who referred each player, credited the referral share of the rake on their winnings
+    pub player_a_referrer: Option<CanonicalAddr>,
+    pub player_b_referrer: Option<CanonicalAddr>,
 
     pub player_a_powerup: Option<u16>,
     pub player_a_powerup_token_id: Option<String>,
@@ -168,16 +540,113 @@ pub struct GameState {
     pub player_b_reward_pick_block: Option<u64>,
 
     // round 0 means second player has not joined, yet
-    // round 1 means submitting hints, and guessing
-    // (no round 2 in first version)
-    // round 3 means in reward round
+    // rounds 1 to rounds_per_game mean submitting hints, and guessing
+    // round rounds_per_game + 1 means in reward round
     pub round: u8,
+    // rules are fixed when the game is created, so config updates do not affect games in progress
+    pub rounds_per_game: u8,
+    pub chip_weights: ChipWeights,
+    pub timeout: u64,
+    // blocks the game waits in the lobby for an opponent before it expires
+    pub lobby_expiration: u64,
     pub round_state: Option<RoundState>,
+    // finished rounds before the current round_state, oldest first
+    pub round_history: Vec<RoundState>,
     pub finished: bool,
     pub result: Option<u8>,
+    // block height when player a created the game, and when the game finished. finished_block is
+    // only set by settle_game, along with finished
+    pub created_block: u64,
+    pub finished_block: Option<u64>,
+    // set when the game was ended by force endgame
+    pub timed_out_player: Option<CanonicalAddr>,
+    // what each player was sent back once the game finished
+    pub player_a_payout: Option<u128>,
+    pub player_b_payout: Option<u128>,
 
     pub jackpot_reward: Option<u128>,
     pub nft_token_id: Option<String>,
+
+    // rake on winning payouts and its split when the game was created, and every fee taken. the
+    // pool gets what is left of each rake after the treasury and referral shares
+    pub rake_bps: u16,
+    pub rake_treasury_bps: u16,
+    pub rake_referral_bps: u16,
+    pub treasury: Option<CanonicalAddr>,
+    pub fees: Vec<FeeRecord>,
+}
+
+/// house rake taken from a player's winning payout and where it went
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FeeRecord {
+    pub payer: CanonicalAddr,
+    pub amount: u128,
+    pub to_pool: u128,
+    pub to_treasury: u128,
+    pub to_referral: u128,
+}
+
+impl GameState {
+    pub fn is_private(&self) -> bool {
+        self.invited_player.is_some() || self.invite_hash.is_some()
+    }
+
+    pub fn reward_round(&self) -> u8 {
+        self.rounds_per_game + 1
+    }
+
+    pub fn in_reward_round(&self) -> bool {
+        self.round == self.reward_round()
+    }
+
+    /// Returns all rounds played so far, including the current round, oldest first
+    pub fn rounds(&self) -> Vec<RoundState> {
+        let mut rounds = self.round_history.clone();
+        if let Some(round_state) = self.round_state.clone() {
+            rounds.push(round_state);
+        }
+        rounds
+    }
+
+    /// Returns the referrer the player gave when joining the game
+    pub fn referrer_of(&self, player: &CanonicalAddr) -> Option<CanonicalAddr> {
+        if *player == self.player_a {
+            self.player_a_referrer.clone()
+        } else {
+            self.player_b_referrer.clone()
+        }
+    }
+
+    /// Returns the total rake taken from the player's payouts
+    pub fn fees_paid_by(&self, player: &CanonicalAddr) -> u128 {
+        self.fees.iter().filter(|fee| fee.payer == *player).map(|fee| fee.amount).sum()
+    }
+
+    /// Returns the players' entropy hashes to mix into each deal
+    pub fn players_entropy(&self) -> Vec<u8> {
+        let mut entropy: Vec<u8> = vec![];
+        if let Some(player_a_entropy) = &self.player_a_entropy {
+            entropy.extend(player_a_entropy);
+        }
+        if let Some(player_b_entropy) = &self.player_b_entropy {
+            entropy.extend(player_b_entropy);
+        }
+        entropy
+    }
+
+    /// Returns the outcome of every round where both players have guessed, oldest first
+    pub fn round_outcomes(&self) -> StdResult<Vec<RoundOutcome>> {
+        let mut outcomes: Vec<RoundOutcome> = vec![];
+        for round_state in self.rounds() {
+            if let (Some(a_result), Some(b_result)) = (round_state.player_a_round_result, round_state.player_b_round_result) {
+                outcomes.push(RoundOutcome::from_results(
+                    &RoundResult::from_u8(a_result)?,
+                    &RoundResult::from_u8(b_result)?,
+                ));
+            }
+        }
+        Ok(outcomes)
+    }
 }
 
 ///
@@ -187,16 +656,34 @@ pub struct GameState {
 fn store_new_game<S: Storage>(
     storage: &mut S,
     player: &CanonicalAddr,
+    stakes: u8,
+    denom: &str,
     wager: u128,
+    invited_player: Option<CanonicalAddr>,
+    invite_hash: Option<Vec<u8>>,
+    entropy: Option<Vec<u8>>,
+    referrer: Option<CanonicalAddr>,
+    block: u64,
 ) -> StdResult<u32> {
+    // rules are fixed when the game is created
+    let config = get_config(storage)?;
+
     let mut storage = PrefixedStorage::new(GAME_PREFIX, storage);
     let mut storage = AppendStoreMut::<GameState, _>::attach_or_create(&mut storage)?;
 
     let game_state = GameState {
         player_a: player.clone(),
         player_b: None,
+        invited_player,
+        invite_hash,
+        stakes,
+        denom: denom.to_string(),
         player_a_wager: Some(wager),
         player_b_wager: None,
+        player_a_entropy: entropy,
+        player_b_entropy: None,
+        player_a_referrer: referrer,
+        player_b_referrer: None,
         player_a_powerup: None,
         player_a_powerup_token_id: None,
         player_a_powerup_applied: false,
@@ -208,11 +695,26 @@ fn store_new_game<S: Storage>(
         player_b_reward_pick: None,
         player_b_reward_pick_block: None,
         round: 0_u8,
+        rounds_per_game: config.rounds_per_game,
+        chip_weights: config.chip_weights(),
+        timeout: config.timeout,
+        lobby_expiration: config.lobby_expiration,
         round_state: None,
+        round_history: vec![],
         finished: false,
         result: None,
+        created_block: block,
+        finished_block: None,
+        timed_out_player: None,
+        player_a_payout: None,
+        player_b_payout: None,
         jackpot_reward: None,
         nft_token_id: None,
+        rake_bps: config.rake_bps,
+        rake_treasury_bps: config.rake_treasury_bps,
+        rake_referral_bps: config.rake_referral_bps,
+        treasury: config.treasury.clone(),
+        fees: vec![],
     };
     storage.push(&game_state)?;
     Ok(storage.len()-1)
@@ -221,9 +723,31 @@ fn store_new_game<S: Storage>(
 pub fn create_new_game<S: Storage>(
     storage: &mut S,
     player: &CanonicalAddr,
+    stakes: u8,
+    denom: &str,
+    wager: u128,
+    entropy: Option<Vec<u8>>,
+    referrer: Option<CanonicalAddr>,
+    block: u64,
+) -> StdResult<()> {
+    let game_idx = store_new_game(storage, player, stakes, denom, wager, None, None, entropy, referrer, block)?;
+    set_current_game(storage, player, Some(game_idx))?;
+    add_to_lobby(storage, stakes, denom, game_idx)
+}
+
+pub fn create_new_private_game<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    stakes: u8,
+    denom: &str,
     wager: u128,
+    invited_player: Option<CanonicalAddr>,
+    invite_hash: Option<Vec<u8>>,
+    entropy: Option<Vec<u8>>,
+    referrer: Option<CanonicalAddr>,
+    block: u64,
 ) -> StdResult<()> {
-    let game_idx = store_new_game(storage, player, wager)?;
+    let game_idx = store_new_game(storage, player, stakes, denom, wager, invited_player, invite_hash, entropy, referrer, block)?;
     set_current_game(storage, player, Some(game_idx))
 }
 
@@ -267,30 +791,100 @@ pub fn update_game_state<S: Storage>(
     storage.set_at(game_idx, game_state)
 }
 
-pub fn is_game_waiting_for_second_player<S: Storage>(
+///
+/// Lobby of open games waiting for a second player, one for each stakes tier and denom
+///
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LobbyEntry {
+    pub game_idx: u32,
+}
+
+pub fn set_lobby<S: Storage>(
+    storage: &mut S,
+    stakes: u8,
+    denom: &str,
+    lobby: &Vec<LobbyEntry>,
+) -> StdResult<()> {
+    let mut storage = PrefixedStorage::multilevel(&[LOBBY_PREFIX, denom.as_bytes()], storage);
+    set_bin_data(&mut storage, &[stakes], lobby)
+}
+
+pub fn get_lobby<S: ReadonlyStorage>(
     storage: &S,
-) -> StdResult<bool> {
-    let storage = ReadonlyPrefixedStorage::new(GAME_PREFIX, storage);
+    stakes: u8,
+    denom: &str,
+) -> Vec<LobbyEntry> {
+    let storage = ReadonlyPrefixedStorage::multilevel(&[LOBBY_PREFIX, denom.as_bytes()], storage);
+    get_bin_data(&storage, &[stakes]).unwrap_or_else(|_| vec![])
+}
 
-    let storage = if let Some(result) = AppendStore::<GameState, _>::attach(&storage) {
-        result?
-    } else {
-        return Ok(false);
-    };
+pub fn add_to_lobby<S: Storage>(
+    storage: &mut S,
+    stakes: u8,
+    denom: &str,
+    game_idx: u32,
+) -> StdResult<()> {
+    let mut lobby = get_lobby(storage, stakes, denom);
+    lobby.push(LobbyEntry { game_idx });
+    set_lobby(storage, stakes, denom, &lobby)
+}
 
-    // get the state of the last game created
-    let game_state = storage.get_at(storage.len()-1)?;
-    Ok(game_state.player_b.is_none())
+pub fn remove_from_lobby<S: Storage>(
+    storage: &mut S,
+    stakes: u8,
+    denom: &str,
+    game_idx: u32,
+) -> StdResult<()> {
+    let mut lobby = get_lobby(storage, stakes, denom);
+    lobby.retain(|entry| entry.game_idx != game_idx);
+    set_lobby(storage, stakes, denom, &lobby)
+}
+
+/// Returns the lobby entries that can still be joined, oldest first, each with the wager the game
+/// was opened with, and the entries of games that have waited their lobby expiration or longer,
+/// which are unfinished and still need to be refunded. Entries for games that have been withdrawn
+/// or joined are left out of both.
+pub fn get_open_lobby_games<S: Storage>(
+    storage: &S,
+    stakes: u8,
+    denom: &str,
+    block: u64,
+) -> StdResult<(Vec<(LobbyEntry, u128)>, Vec<LobbyEntry>)> {
+    let mut open_games: Vec<(LobbyEntry, u128)> = vec![];
+    let mut expired_games: Vec<LobbyEntry> = vec![];
+    for entry in get_lobby(storage, stakes, denom) {
+        let game_state = get_game_state(storage, entry.game_idx)?;
+        if game_state.finished || game_state.player_b.is_some() {
+            continue;
+        }
+        if block >= game_state.created_block + game_state.lobby_expiration {
+            expired_games.push(entry);
+        } else {
+            open_games.push((entry, game_state.player_a_wager.unwrap_or(0)));
+        }
+    }
+    Ok((open_games, expired_games))
 }
 
 //
 // RoundState
 //
 
-pub fn create_new_round<S: Storage>(
-    storage: &S,
-    block: u64,
-) -> StdResult<RoundState> {
+/// Deals the chips and first hints of a round from its deal seed. Once the seed is revealed,
+/// anyone can repeat the deal: the rng is ChaCha20 seeded with sha256(seed || "deal"), and the bag chip,
+/// player a chip and player b chip are picked in that order, color then shape. Each pick rolls
+/// next_u64 modulo the total weight of the options left, walks the options left in order, and
+/// swap-removes the picked one (starting from red, green, blue, black and triangle, square,
+/// circle, star). The last roll, modulo 2, decides if player a gets the color hint (0) or the
+/// shape hint (1).
+pub fn deal_round(
+    deal_seed: &[u8],
+    chip_weights: &ChipWeights,
+) -> StdResult<Deal> {
+    // the commitment is sha256(seed), so the rng is seeded apart from it or the commitment would give the deal away
+    let mut rng = Prng::new(deal_seed, b"deal");
+
     let mut color_options: Vec<Color> = vec!(
         Color::Red,
         Color::Green,
@@ -306,18 +900,18 @@ pub fn create_new_round<S: Storage>(
     );
 
     let bag_chip = Chip {
-        color: get_random_color(storage, &mut color_options, true)?.unwrap(),
-        shape: get_random_shape(storage, &mut shape_options, true)?.unwrap(),
+        color: get_random_color(&mut rng, chip_weights, &mut color_options, true)?.unwrap(),
+        shape: get_random_shape(&mut rng, chip_weights, &mut shape_options, true)?.unwrap(),
     };
 
     let player_a_chip = Chip {
-        color: get_random_color(storage, &mut color_options, true)?.unwrap(),
-        shape: get_random_shape(storage, &mut shape_options, true)?.unwrap(),
+        color: get_random_color(&mut rng, chip_weights, &mut color_options, true)?.unwrap(),
+        shape: get_random_shape(&mut rng, chip_weights, &mut shape_options, true)?.unwrap(),
     };
 
     let player_b_chip = Chip {
-        color: get_random_color(storage, &mut color_options, true)?.unwrap(),
-        shape: get_random_shape(storage, &mut shape_options, true)?.unwrap(),
+        color: get_random_color(&mut rng, chip_weights, &mut color_options, true)?.unwrap(),
+        shape: get_random_shape(&mut rng, chip_weights, &mut shape_options, true)?.unwrap(),
     };
 
     let player_a_first_hint: Hint;
@@ -340,7 +934,7 @@ pub fn create_new_round<S: Storage>(
         _ => { return Err(StdError::generic_err("Error calculating available shape hint"));}
     }
 
-    let roll = get_random_number(storage) % 2;
+    let roll = rng.next_u64() % 2;
     if roll == 0 {
         // give player a color hint, give player b shape hint
         player_a_first_hint = available_color;
@@ -351,14 +945,40 @@ pub fn create_new_round<S: Storage>(
         player_b_first_hint = available_color;
     }
 
+    Ok(Deal {
+        bag_chip,
+        player_a_chip,
+        player_b_chip,
+        player_a_first_hint,
+        player_b_first_hint,
+    })
+}
+
+pub fn create_new_round(
+    rng: &mut Prng,
+    block: u64,
+    chip_weights: &ChipWeights,
+    players_entropy: &[u8],
+) -> StdResult<RoundState> {
+    // each round is dealt from a seed of its own, only its hash is shown until the game is finished.
+    // mixing in the players' entropy means neither the contract's entropy nor one player decides the deal
+    let contract_seed = rng.rand_bytes();
+    let mut seed_source = contract_seed.to_vec();
+    seed_source.extend(players_entropy);
+    let deal_seed = sha_256(&seed_source);
+    let deal = deal_round(&deal_seed, chip_weights)?;
+
     Ok(RoundState {
         stage: RoundStage::Initialized.u8_val(),
         round_start_block: block,
-        bag_chip: bag_chip.to_stored(),
-        player_a_chip: player_a_chip.to_stored(),
-        player_b_chip: player_b_chip.to_stored(),
-        player_a_first_hint: player_a_first_hint.u8_val(),
-        player_b_first_hint: player_b_first_hint.u8_val(),
+        contract_seed: contract_seed.to_vec(),
+        deal_seed: deal_seed.to_vec(),
+        deal_commitment: sha_256(&deal_seed).to_vec(),
+        bag_chip: deal.bag_chip.to_stored(),
+        player_a_chip: deal.player_a_chip.to_stored(),
+        player_b_chip: deal.player_b_chip.to_stored(),
+        player_a_first_hint: deal.player_a_first_hint.u8_val(),
+        player_b_first_hint: deal.player_b_first_hint.u8_val(),
         player_a_first_submit: None,
         player_a_first_submit_block: None,
         player_b_first_submit: None,
@@ -389,10 +1009,51 @@ pub fn set_current_game<S: Storage>(
     player: &CanonicalAddr,
     game_idx: Option<u32>,
 ) -> StdResult<()> {
+    // every game the player enters is kept in their game history
+    if let Some(game_idx) = game_idx {
+        add_player_game(storage, player, game_idx)?;
+    }
     let mut storage = PrefixedStorage::new(CURRENT_GAME_PREFIX, storage);
     set_bin_data(&mut storage, player.as_slice(), &game_idx)
 }
 
+pub fn add_player_game<S: Storage>(
+    storage: &mut S,
+    player: &CanonicalAddr,
+    game_idx: u32,
+) -> StdResult<()> {
+    let mut storage = PrefixedStorage::multilevel(&[PLAYER_GAMES_PREFIX, player.as_slice()], storage);
+    let mut storage = AppendStoreMut::<u32, _>::attach_or_create(&mut storage)?;
+    storage.push(&game_idx)
+}
+
+/// Returns up to `take` of the player's game indices, newest first, after skipping the `skip`
+/// newest ones, along with the total number of games the player has entered.
+pub fn get_player_games<S: ReadonlyStorage>(
+    storage: &S,
+    player: &CanonicalAddr,
+    skip: u32,
+    take: u32,
+) -> StdResult<(Vec<u32>, u32)> {
+    let storage = ReadonlyPrefixedStorage::multilevel(&[PLAYER_GAMES_PREFIX, player.as_slice()], storage);
+
+    // player has not entered any games yet
+    let storage = if let Some(result) = AppendStore::<u32, _>::attach(&storage) {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+    let count = storage.len();
+    let game_idxs: StdResult<Vec<u32>> = storage
+        .iter()
+        .rev()
+        .skip(skip as usize)
+        .take(take as usize)
+        .collect();
+
+    game_idxs.map(|g| (g, count))
+}
+
 pub fn get_current_game<S: ReadonlyStorage>(
     storage: &S,
     player: &CanonicalAddr,
@@ -401,38 +1062,187 @@ pub fn get_current_game<S: ReadonlyStorage>(
     get_bin_data(&storage, player.as_slice()).unwrap_or_else(|_| None)
 }
 
-pub fn set_won<S: Storage>(
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PlayerStats {
+    // games played to the end, including timeouts but not withdrawals
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub both_lose: u32,
+    // tied practice games, tied games in other tiers go to the reward round
+    pub ties: u32,
+    // reward round outcomes
+    pub jackpots_won: u32,
+    pub nfts_won: u32,
+    pub no_rewards: u32,
+    pub timeout_wins: u32,
+    pub timeout_losses: u32,
+    pub withdrawals: u32,
+    // wagers in games played, and everything paid back out (refunds, winnings and jackpots),
+    // added up in the smallest unit of whichever denom each game was played in
+    pub total_wagered: u128,
+    pub total_payout: u128,
+}
+
+impl PlayerStats {
+    pub fn record(&mut self, outcome: &PlayerOutcome, wager: u128, payout: u128) {
+        if *outcome == PlayerOutcome::Withdrew {
+            // wager was refunded before the game started
+            self.withdrawals += 1;
+            return;
+        }
+        self.games_played += 1;
+        self.total_wagered += wager;
+        self.total_payout += payout;
+        match outcome {
+            PlayerOutcome::Won => self.wins += 1,
+            PlayerOutcome::Lost => self.losses += 1,
+            PlayerOutcome::BothLose => self.both_lose += 1,
+            PlayerOutcome::Tie => self.ties += 1,
+            PlayerOutcome::Jackpot => self.jackpots_won += 1,
+            PlayerOutcome::Nft => self.nfts_won += 1,
+            PlayerOutcome::NoReward => self.no_rewards += 1,
+            PlayerOutcome::TimeoutWon => self.timeout_wins += 1,
+            PlayerOutcome::TimeoutLost => self.timeout_losses += 1,
+            PlayerOutcome::Withdrew => {},
+        }
+    }
+
+    // net winnings can be negative, so keep them signed
+    pub fn net_winnings(&self) -> i128 {
+        self.total_payout as i128 - self.total_wagered as i128
+    }
+}
+
+pub fn set_player_stats<S: Storage>(
     storage: &mut S,
     player: &CanonicalAddr,
-    won: u32,
+    stats: &PlayerStats,
 ) -> StdResult<()> {
-    let mut storage = PrefixedStorage::new(WON_PREFIX, storage);
-    set_bin_data(&mut storage, player.as_slice(), &won)
+    let mut storage = PrefixedStorage::new(PLAYER_STATS_PREFIX, storage);
+    set_bin_data(&mut storage, player.as_slice(), stats)
 }
 
-pub fn get_won<S: ReadonlyStorage>(
+pub fn get_player_stats<S: ReadonlyStorage>(
     storage: &S,
     player: &CanonicalAddr,
-) -> u32 {
-    let storage = ReadonlyPrefixedStorage::new(WON_PREFIX, storage);
-    get_bin_data(&storage, player.as_slice()).unwrap_or_else(|_| 0_u32)
+) -> PlayerStats {
+    let storage = ReadonlyPrefixedStorage::new(PLAYER_STATS_PREFIX, storage);
+    get_bin_data(&storage, player.as_slice()).unwrap_or_else(|_| PlayerStats::default())
 }
 
-pub fn set_lost<S: Storage>(
+pub fn record_player_outcome<S: Storage>(
     storage: &mut S,
     player: &CanonicalAddr,
-    lost: u32,
+    outcome: PlayerOutcome,
+    wager: u128,
+    payout: u128,
 ) -> StdResult<()> {
-    let mut storage = PrefixedStorage::new(LOST_PREFIX, storage);
-    set_bin_data(&mut storage, player.as_slice(), &lost)
+    let mut stats = get_player_stats(storage, player);
+    stats.record(&outcome, wager, payout);
+    set_player_stats(storage, player, &stats)
+}
+
+///
+/// Leaderboard of players who opted in to show their stats publicly. The leaderboard list only
+/// holds the players currently opted in. Each player's entry is stored on its own with their
+/// position in the list, so opting out swap-removes them from it.
+///
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub player: CanonicalAddr,
+    pub display_name: Option<String>,
+    // position of the player in the leaderboard list
+    pub position: u32,
+}
+
+fn set_leaderboard_entry<S: Storage>(
+    storage: &mut S,
+    entry: &LeaderboardEntry,
+) -> StdResult<()> {
+    let mut storage = PrefixedStorage::new(LEADERBOARD_ENTRY_PREFIX, storage);
+    set_bin_data(&mut storage, entry.player.as_slice(), entry)
+}
+
+fn get_leaderboard_entry<S: ReadonlyStorage>(
+    storage: &S,
+    player: &CanonicalAddr,
+) -> Option<LeaderboardEntry> {
+    let storage = ReadonlyPrefixedStorage::new(LEADERBOARD_ENTRY_PREFIX, storage);
+    get_bin_data(&storage, player.as_slice()).ok()
 }
 
-pub fn get_lost<S: ReadonlyStorage>(
+/// Returns the entries of the players currently opted in
+pub fn get_leaderboard<S: ReadonlyStorage>(
     storage: &S,
+) -> StdResult<Vec<LeaderboardEntry>> {
+    let players_storage = ReadonlyPrefixedStorage::new(LEADERBOARD_PREFIX, storage);
+    let players_storage = match AppendStore::<CanonicalAddr, _>::attach(&players_storage) {
+        Some(result) => result?,
+        None => return Ok(vec![]),
+    };
+    let mut leaderboard: Vec<LeaderboardEntry> = vec![];
+    for player in players_storage.iter() {
+        if let Some(entry) = get_leaderboard_entry(storage, &player?) {
+            leaderboard.push(entry);
+        }
+    }
+    Ok(leaderboard)
+}
+
+/// Adds the player to the leaderboard or updates their display name, or takes them off
+/// if `opt_in` is false.
+pub fn set_leaderboard_opt_in<S: Storage>(
+    storage: &mut S,
     player: &CanonicalAddr,
-) -> u32 {
-    let storage = ReadonlyPrefixedStorage::new(LOST_PREFIX, storage);
-    get_bin_data(&storage, player.as_slice()).unwrap_or_else(|_| 0_u32)
+    opt_in: bool,
+    display_name: Option<String>,
+) -> StdResult<()> {
+    let entry = get_leaderboard_entry(storage, player);
+    if !opt_in {
+        if let Some(entry) = entry {
+            remove_from_leaderboard(storage, &entry)?;
+        }
+        return Ok(());
+    }
+    let position = match entry {
+        Some(entry) => entry.position,
+        None => {
+            let mut players_storage = PrefixedStorage::new(LEADERBOARD_PREFIX, storage);
+            let mut players_storage = AppendStoreMut::<CanonicalAddr, _>::attach_or_create(&mut players_storage)?;
+            players_storage.push(player)?;
+            players_storage.len() - 1
+        },
+    };
+    set_leaderboard_entry(storage, &LeaderboardEntry { player: player.clone(), display_name, position })
+}
+
+// moves the last player in the leaderboard list into the removed player's position
+fn remove_from_leaderboard<S: Storage>(
+    storage: &mut S,
+    entry: &LeaderboardEntry,
+) -> StdResult<()> {
+    let moved = {
+        let mut players_storage = PrefixedStorage::new(LEADERBOARD_PREFIX, storage);
+        let mut players_storage = AppendStoreMut::<CanonicalAddr, _>::attach_or_create(&mut players_storage)?;
+        let last = players_storage.pop()?;
+        if last == entry.player {
+            None
+        } else {
+            players_storage.set_at(entry.position, &last)?;
+            Some(last)
+        }
+    };
+    if let Some(moved) = moved {
+        if let Some(mut moved_entry) = get_leaderboard_entry(storage, &moved) {
+            moved_entry.position = entry.position;
+            set_leaderboard_entry(storage, &moved_entry)?;
+        }
+    }
+    let mut storage = PrefixedStorage::new(LEADERBOARD_ENTRY_PREFIX, storage);
+    storage.remove(entry.player.as_slice());
+    Ok(())
 }
 
 //
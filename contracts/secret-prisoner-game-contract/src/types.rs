@@ -1,6 +1,7 @@
 use cosmwasm_std::{
     StdError, StdResult, 
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub const RED: u8 = 0b10000000u8;
@@ -52,6 +53,100 @@ impl GameStage {
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
 #[repr(u8)]
+pub enum StakesTier {
+    Practice,
+    Low,
+    Medium,
+    High,
+}
+
+impl StakesTier {
+    pub fn u8_val(&self) -> u8 {
+        match self {
+            StakesTier::Practice => 0_u8,
+            StakesTier::Low => 1_u8,
+            StakesTier::Medium => 2_u8,
+            StakesTier::High => 3_u8,
+        }
+    }
+
+    pub fn from_u8(val: u8) -> StdResult<StakesTier> {
+        match val {
+            0_u8 => Ok(StakesTier::Practice),
+            1_u8 => Ok(StakesTier::Low),
+            2_u8 => Ok(StakesTier::Medium),
+            3_u8 => Ok(StakesTier::High),
+            _ => Err(StdError::generic_err("Invalid stakes value")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[repr(u8)]
+pub enum LobbyPolicy {
+    Fifo,
+    Random,
+}
+
+impl LobbyPolicy {
+    pub fn u8_val(&self) -> u8 {
+        match self {
+            LobbyPolicy::Fifo => 0_u8,
+            LobbyPolicy::Random => 1_u8,
+        }
+    }
+
+    pub fn from_u8(val: u8) -> StdResult<LobbyPolicy> {
+        match val {
+            0_u8 => Ok(LobbyPolicy::Fifo),
+            1_u8 => Ok(LobbyPolicy::Random),
+            _ => Err(StdError::generic_err("Invalid lobby policy value")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[repr(u8)]
+pub enum PoolChangeReason {
+    Seed,
+    BothLose,
+    Timeout,
+    Jackpot,
+    Rake,
+    Funding,
+    Withdrawal,
+}
+
+impl PoolChangeReason {
+    pub fn u8_val(&self) -> u8 {
+        match self {
+            PoolChangeReason::Seed => 0_u8,
+            PoolChangeReason::BothLose => 1_u8,
+            PoolChangeReason::Timeout => 2_u8,
+            PoolChangeReason::Jackpot => 3_u8,
+            PoolChangeReason::Rake => 4_u8,
+            PoolChangeReason::Funding => 5_u8,
+            PoolChangeReason::Withdrawal => 6_u8,
+        }
+    }
+
+    pub fn from_u8(val: u8) -> StdResult<PoolChangeReason> {
+        match val {
+            0_u8 => Ok(PoolChangeReason::Seed),
+            1_u8 => Ok(PoolChangeReason::BothLose),
+            2_u8 => Ok(PoolChangeReason::Timeout),
+            3_u8 => Ok(PoolChangeReason::Jackpot),
+            4_u8 => Ok(PoolChangeReason::Rake),
+            5_u8 => Ok(PoolChangeReason::Funding),
+            6_u8 => Ok(PoolChangeReason::Withdrawal),
+            _ => Err(StdError::generic_err("Invalid pool change reason value")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
 pub enum GameResult {
     AWon,
     BWon,
@@ -125,7 +220,8 @@ impl RoundStage {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum RoundResult {
     BagCorrect,
@@ -158,7 +254,56 @@ impl RoundResult {
     }
 }
 
-#[derive(Debug, Hash, Serialize, Deserialize, Clone, Eq, PartialEq)]
+// outcome of a single round, scored from both players' round results
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub enum RoundOutcome {
+    AWon,
+    BWon,
+    BothLose,
+    Tie,
+}
+
+impl RoundOutcome {
+    pub fn from_results(player_a_round_result: &RoundResult, player_b_round_result: &RoundResult) -> RoundOutcome {
+        match (player_a_round_result, player_b_round_result) {
+            // same correct guess or both abstain
+            (RoundResult::BagCorrect, RoundResult::BagCorrect) |
+            (RoundResult::OpponentCorrect, RoundResult::OpponentCorrect) |
+            (RoundResult::Abstain, RoundResult::Abstain) => RoundOutcome::Tie,
+            // guessing opponent beats everything else
+            (RoundResult::OpponentCorrect, _) => RoundOutcome::AWon,
+            (_, RoundResult::OpponentCorrect) => RoundOutcome::BWon,
+            // guessing bag beats a wrong guess or abstaining
+            (RoundResult::BagCorrect, _) => RoundOutcome::AWon,
+            (_, RoundResult::BagCorrect) => RoundOutcome::BWon,
+            // abstaining beats a wrong guess
+            (RoundResult::Abstain, _) => RoundOutcome::AWon,
+            (_, RoundResult::Abstain) => RoundOutcome::BWon,
+            // both guessed wrong
+            _ => RoundOutcome::BothLose,
+        }
+    }
+}
+
+// how a game ended for one of its players, used to update the player's stats
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub enum PlayerOutcome {
+    Won,
+    Lost,
+    BothLose,
+    // round wins were tied in a practice game, so there was no reward round
+    Tie,
+    Jackpot,
+    Nft,
+    // both players picked the same reward
+    NoReward,
+    TimeoutWon,
+    TimeoutLost,
+    Withdrew,
+}
+
+#[derive(Debug, Hash, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Color {
     Red,
@@ -207,7 +352,8 @@ impl Color {
     }
 }
 
-#[derive(Debug, Hash, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Hash, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Shape {
     Triangle,
@@ -256,7 +402,7 @@ impl Shape {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Chip {
     pub color: Color,
     pub shape: Shape,
@@ -304,7 +450,8 @@ impl StoredChip {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Hint {
     NobodyHasRed,
@@ -447,7 +594,8 @@ impl Hint {
     */
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Target {
     Bag,
@@ -474,7 +622,7 @@ impl Target {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Guess {
     pub target: Target,
     pub color: Option<Color>,